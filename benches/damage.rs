@@ -0,0 +1,53 @@
+//! Compares presenting a frame in which one cell changed, copying only the damaged rows or the
+//! whole screen, with presenting a frame in which every cell changed. Run with
+//! `cargo bench --features bench`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use os_terminal_ratatui::FrameBench;
+
+const WIDTH: usize = 1920;
+const HEIGHT: usize = 1080;
+
+/// Moves to the middle of the screen and writes a single character there.
+fn one_cell(c: char) -> Vec<u8> {
+    format!("\x1b[20;40H{}", c).into_bytes()
+}
+
+/// Clears the screen and fills every cell with the character.
+fn full_screen(c: char) -> Vec<u8> {
+    let mut output = b"\x1b[H\x1b[2J".to_vec();
+    output.extend(c.to_string().repeat(400 * 200).bytes());
+    output
+}
+
+fn redraw(c: &mut Criterion) {
+    let frames = [['a', 'b'].map(one_cell), ['a', 'b'].map(full_screen)];
+
+    let mut bench = FrameBench::new(WIDTH, HEIGHT).unwrap();
+    let mut i = 0;
+    c.bench_function("one cell, damaged rows", |b| {
+        b.iter(|| {
+            i += 1;
+            bench.present(&frames[0][i % 2])
+        })
+    });
+
+    let mut bench = FrameBench::new(WIDTH, HEIGHT).unwrap();
+    c.bench_function("one cell, whole screen", |b| {
+        b.iter(|| {
+            i += 1;
+            bench.present_all(&frames[0][i % 2])
+        })
+    });
+
+    let mut bench = FrameBench::new(WIDTH, HEIGHT).unwrap();
+    c.bench_function("full screen", |b| {
+        b.iter(|| {
+            i += 1;
+            bench.present(&frames[1][i % 2])
+        })
+    });
+}
+
+criterion_group!(benches, redraw);
+criterion_main!(benches);
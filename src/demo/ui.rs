@@ -11,7 +11,7 @@ use ratatui::{
     Frame,
 };
 
-use crate::tui::app::App;
+use crate::demo::App;
 
 pub fn draw(frame: &mut Frame, app: &mut App) {
     let chunks = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).split(frame.area());
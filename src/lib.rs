@@ -0,0 +1,17 @@
+//! Runs ratatui applications inside a window, using os-terminal to render them, or in the
+//! terminal the process was started from.
+
+mod options;
+mod terminal;
+mod theme;
+mod tui;
+
+pub use options::{FontOptions, Options};
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub use terminal::FrameBench;
+pub use terminal::{PixelOverlay, Pixels, RenderMode, run_headless, run_playback, run_windowed};
+#[cfg(target_os = "linux")]
+pub use terminal::run_command;
+pub use theme::{Rgb, Theme};
+pub use tui::{TuiApp, run_in_terminal};
@@ -1,12 +1,80 @@
 use std::error::Error;
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "linux")]
+use std::process::Command;
 
-mod tui;
-mod terminal;
+use os_terminal_ratatui::{
+    Options, RenderMode, run_headless, run_in_terminal, run_playback, run_windowed,
+};
+#[cfg(target_os = "linux")]
+use os_terminal_ratatui::run_command;
+
+mod demo;
+
+// the options which can come before the mode, and what has to follow each of them.
+const OPTIONS: [(&str, &str); 6] = [
+    ("--config", "a config file"),
+    ("--record", "a file"),
+    ("--font", "a font file"),
+    ("--font-size", "a size in points"),
+    ("--fallback-font", "a font file"),
+    ("--theme", "a theme name"),
+];
 
 fn main() -> Result<(), Box<dyn Error>> {
-    if let Some("--no-win") = std::env::args().skip(1).next().as_deref() {
-        tui::run_no_win()
-    } else {
-        terminal::run()
+    let app = demo::App::new("Crossterm Demo", true);
+    let mut args = std::env::args().skip(1).peekable();
+    // options come before the mode, and are applied after the config file.
+    let mut config = Options::default_config_path().filter(|path| path.exists());
+    let mut overrides = Vec::new();
+    while let Some(option) = args.next_if(|arg| OPTIONS.iter().any(|(name, _)| arg == name)) {
+        let Some(value) = args.next() else {
+            let (_, expected) = OPTIONS.iter().find(|(name, _)| *name == option).unwrap();
+            return Err(format!("{} requires {}", option, expected).into());
+        };
+        match option.as_str() {
+            "--config" => config = Some(PathBuf::from(value)),
+            _ => overrides.push((option, value)),
+        }
+    }
+
+    let mut options = Options::default();
+    if let Some(path) = config {
+        options.load_config(&path)?;
+    }
+    for (option, value) in overrides {
+        match option.as_str() {
+            "--record" => options.record = Some(PathBuf::from(value)),
+            "--font" => options.set("font", &value)?,
+            "--font-size" => options.set("font_size", &value)?,
+            "--fallback-font" => options.set("fallback_font", &value)?,
+            "--theme" => options.set("theme", &value)?,
+            _ => unreachable!(),
+        }
+    }
+
+    match args.next().as_deref() {
+        Some("--no-win") => run_in_terminal(app, options),
+        Some("--pixel") => {
+            options.render_mode = RenderMode::Pixel;
+            run_windowed(app, options)
+        }
+        Some("--headless") => {
+            let script = args.next().ok_or("--headless requires a script file")?;
+            run_headless(app, options, Path::new(&script))
+        }
+        Some("--play") => {
+            let recording = args.next().ok_or("--play requires a recording")?;
+            run_playback(Path::new(&recording), options)
+        }
+        #[cfg(target_os = "linux")]
+        Some("--exec") => {
+            // everything after --exec is the command line, the user's shell if there is none.
+            let program = args.next().or_else(|| std::env::var("SHELL").ok());
+            let mut command = Command::new(program.as_deref().unwrap_or("/bin/sh"));
+            command.args(args);
+            run_command(command, options)
+        }
+        _ => run_windowed(app, options),
     }
 }
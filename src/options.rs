@@ -0,0 +1,164 @@
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ratatui::crossterm::event::KeyboardEnhancementFlags;
+
+use crate::terminal::RenderMode;
+use crate::theme::Theme;
+
+/// Settings for running a [`TuiApp`](crate::TuiApp). Settings which only apply to the window are
+/// ignored by [`run_in_terminal`](crate::run_in_terminal).
+pub struct Options {
+    /// How often [`TuiApp::tick`](crate::TuiApp::tick) is called.
+    pub tick_rate: Duration,
+    /// The title of the window.
+    pub title: String,
+    /// How the application is rendered into the window.
+    pub render_mode: RenderMode,
+    /// An asciicast file to record the session to. Recording needs the terminal to see the
+    /// output, so it isn't available in [`RenderMode::Pixel`].
+    pub record: Option<PathBuf>,
+    /// The font the window draws text with.
+    pub font: FontOptions,
+    /// The name of the theme the window starts out with.
+    pub theme: String,
+    /// The themes the window can switch between with control, shift and T, in order.
+    pub themes: Vec<Theme>,
+    /// Whether programs run by [`run_command`](crate::run_command) may read the clipboard
+    /// with OSC 52. Writing to it is always allowed.
+    pub clipboard_read: bool,
+    /// The keyboard enhancements the application asks for, which are pushed as crossterm's
+    /// [`PushKeyboardEnhancementFlags`](ratatui::crossterm::event::PushKeyboardEnhancementFlags)
+    /// does. Key repeats and releases are only reported with
+    /// [`REPORT_EVENT_TYPES`](KeyboardEnhancementFlags::REPORT_EVENT_TYPES), repeats come as
+    /// presses otherwise.
+    pub keyboard_enhancement: KeyboardEnhancementFlags,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            tick_rate: Duration::from_millis(250),
+            title: String::from("Terminal"),
+            render_mode: RenderMode::Terminal,
+            record: None,
+            font: FontOptions::default(),
+            theme: String::from("default"),
+            themes: Theme::builtin(),
+            clipboard_read: false,
+            keyboard_enhancement: KeyboardEnhancementFlags::empty(),
+        }
+    }
+}
+
+/// The font the window draws text with. Cells are sized by the primary font, characters it has
+/// no glyph for are drawn with the first fallback font which does.
+pub struct FontOptions {
+    /// A TrueType or OpenType font file, the built in font if there is none.
+    pub path: Option<PathBuf>,
+    /// The size of the text, in points.
+    pub size: f32,
+    /// Fonts to look for missing characters in, in order.
+    pub fallbacks: Vec<PathBuf>,
+}
+
+impl Default for FontOptions {
+    fn default() -> Self {
+        Self {
+            path: None,
+            size: 10.0,
+            fallbacks: Vec::new(),
+        }
+    }
+}
+
+impl Options {
+    /// Where the settings are read from when no config file is given,
+    /// `$XDG_CONFIG_HOME/os-terminal-ratatui/config` or `~/.config/os-terminal-ratatui/config`.
+    pub fn default_config_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_home.join("os-terminal-ratatui").join("config"))
+    }
+
+    /// Applies the settings in a config file. Each line holds one `name = value` setting, blank
+    /// lines and lines starting with `#` are ignored. The names are the ones [`Options::set`]
+    /// accepts.
+    pub fn load_config(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let config = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read config {}: {}", path.display(), e))?;
+        for (number, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| format!("{}:{}: {}", path.display(), number + 1, message);
+            let (name, value) = line
+                .split_once('=')
+                .ok_or_else(|| error(format!("expected name = value, found {:?}", line)))?;
+            self.set(name.trim(), value.trim()).map_err(error)?;
+        }
+        Ok(())
+    }
+
+    /// Changes a setting by name, as found in a config file:
+    ///
+    /// | name                 | value                                              |
+    /// |----------------------|----------------------------------------------------|
+    /// | `font`               | a font file                                        |
+    /// | `font_size`          | the size of the text, in points                    |
+    /// | `fallback_font`      | a font file, added to the end of the chain         |
+    /// | `theme`              | the name of the theme to start out with            |
+    /// | `palette.NAME.COLOR` | a color of the theme called `NAME`, see [`Theme::set`] |
+    /// | `clipboard_read`     | `allow` or `deny` reading the clipboard            |
+    ///
+    /// A palette which isn't one of the themes yet is added as a copy of the first theme, so it
+    /// only needs to give the colors which are different.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if let Some(palette) = name.strip_prefix("palette.") {
+            let (theme, color) = palette
+                .rsplit_once('.')
+                .ok_or_else(|| format!("expected palette.NAME.COLOR, found {:?}", name))?;
+            return self.theme_mut(theme).set(color, value);
+        }
+        match name {
+            "font" => self.font.path = Some(PathBuf::from(value)),
+            "font_size" => {
+                self.font.size = value
+                    .parse()
+                    .map_err(|_| format!("font_size {:?} is not a number", value))?;
+            }
+            "fallback_font" => self.font.fallbacks.push(PathBuf::from(value)),
+            "theme" => self.theme = value.to_string(),
+            "clipboard_read" => {
+                self.clipboard_read = match value {
+                    "allow" => true,
+                    "deny" => false,
+                    _ => return Err(format!("clipboard_read {:?} is not allow or deny", value)),
+                };
+            }
+            _ => return Err(format!("unknown setting {:?}", name)),
+        }
+        Ok(())
+    }
+
+    /// Returns the theme with the given name, adding it if there is none.
+    fn theme_mut(&mut self, name: &str) -> &mut Theme {
+        let index = match self.themes.iter().position(|theme| theme.name == name) {
+            Some(index) => index,
+            None => {
+                let mut theme = match self.themes.first() {
+                    Some(theme) => theme.clone(),
+                    None => Theme::builtin().remove(0),
+                };
+                theme.name = name.to_string();
+                self.themes.push(theme);
+                self.themes.len() - 1
+            }
+        };
+        &mut self.themes[index]
+    }
+}
@@ -0,0 +1,166 @@
+use std::sync::{Arc, Mutex};
+
+use os_terminal::ClipboardHandler;
+
+use crate::terminal::log;
+
+/// Where pasted text comes from.
+#[derive(Clone, Copy)]
+pub(crate) enum PasteSource {
+    Clipboard,
+    /// The primary selection, the text last selected anywhere. Only Linux has one, elsewhere
+    /// the clipboard is used instead.
+    Primary,
+}
+
+/// Something which can hold copied text, the system clipboard or a stand in for it.
+pub(crate) trait ClipboardBackend: Send {
+    /// Returns the text to paste, none if there is nothing to paste.
+    fn get_text(&mut self, source: PasteSource) -> Result<Option<String>, String>;
+
+    fn set_text(&mut self, text: String) -> Result<(), String>;
+}
+
+impl ClipboardBackend for arboard::Clipboard {
+    fn get_text(&mut self, source: PasteSource) -> Result<Option<String>, String> {
+        let text = match source {
+            #[cfg(target_os = "linux")]
+            PasteSource::Primary => {
+                use arboard::{GetExtLinux, LinuxClipboardKind};
+                self.get().clipboard(LinuxClipboardKind::Primary).text()
+            }
+            _ => arboard::Clipboard::get_text(self),
+        };
+        match text {
+            Ok(text) => Ok(Some(text)),
+            // an empty clipboard, or one holding something other than text.
+            Err(arboard::Error::ContentNotAvailable) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn set_text(&mut self, text: String) -> Result<(), String> {
+        arboard::Clipboard::set_text(self, text).map_err(|e| e.to_string())
+    }
+}
+
+/// A clipboard which only exists inside the process, for when there is no system clipboard
+/// and for headless runs, which must not touch it. It has no primary selection, pastes from
+/// either come from the clipboard.
+#[derive(Default)]
+pub(crate) struct MemoryClipboard(Option<String>);
+
+impl ClipboardBackend for MemoryClipboard {
+    fn get_text(&mut self, _source: PasteSource) -> Result<Option<String>, String> {
+        Ok(self.0.clone())
+    }
+
+    fn set_text(&mut self, text: String) -> Result<(), String> {
+        self.0 = Some(text);
+        Ok(())
+    }
+}
+
+/// The clipboard shared by everything which copies and pastes: the terminal, clipboard
+/// sequences in the output and the paste shortcuts. Errors are logged rather than returned,
+/// a failed copy or paste shouldn't end the session.
+#[derive(Clone)]
+pub(crate) struct Clipboard(Arc<Mutex<Box<dyn ClipboardBackend>>>);
+
+impl Clipboard {
+    /// Uses the system clipboard, or one in memory if there is no system clipboard to use.
+    pub(crate) fn new() -> Self {
+        match arboard::Clipboard::new() {
+            Ok(clipboard) => Self::with_backend(Box::new(clipboard)),
+            Err(e) => {
+                log(format_args!(
+                    "the system clipboard is not available, copied text stays in the window: {}",
+                    e
+                ));
+                Self::with_backend(Box::<MemoryClipboard>::default())
+            }
+        }
+    }
+
+    pub(crate) fn with_backend(backend: Box<dyn ClipboardBackend>) -> Self {
+        Self(Arc::new(Mutex::new(backend)))
+    }
+
+    pub(crate) fn paste(&self, source: PasteSource) -> Option<String> {
+        self.0.lock().unwrap().get_text(source).unwrap_or_else(|e| {
+            log(format_args!("could not paste: {}", e));
+            None
+        })
+    }
+
+    pub(crate) fn copy(&self, text: String) {
+        if let Err(e) = self.0.lock().unwrap().set_text(text) {
+            log(format_args!("could not copy: {}", e));
+        }
+    }
+}
+
+impl ClipboardHandler for Clipboard {
+    fn get_text(&mut self) -> Option<String> {
+        self.paste(PasteSource::Clipboard)
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.copy(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clipboard which can't be used, like a system clipboard with no provider behind it.
+    struct BrokenClipboard;
+
+    impl ClipboardBackend for BrokenClipboard {
+        fn get_text(&mut self, _source: PasteSource) -> Result<Option<String>, String> {
+            Err("no provider".to_string())
+        }
+
+        fn set_text(&mut self, _text: String) -> Result<(), String> {
+            Err("no provider".to_string())
+        }
+    }
+
+    #[test]
+    fn memory_clipboard_holds_the_last_copy() {
+        let mut backend: Box<dyn ClipboardBackend> = Box::<MemoryClipboard>::default();
+        assert_eq!(backend.get_text(PasteSource::Clipboard), Ok(None));
+        backend.set_text("one".to_string()).unwrap();
+        backend.set_text("two".to_string()).unwrap();
+        assert_eq!(
+            backend.get_text(PasteSource::Clipboard),
+            Ok(Some("two".to_string()))
+        );
+        assert_eq!(
+            backend.get_text(PasteSource::Primary),
+            Ok(Some("two".to_string()))
+        );
+    }
+
+    #[test]
+    fn terminal_copies_and_pastes_through_the_backend() {
+        let clipboard = Clipboard::with_backend(Box::<MemoryClipboard>::default());
+        let mut handler: Box<dyn ClipboardHandler> = Box::new(clipboard.clone());
+        assert_eq!(handler.get_text(), None);
+        handler.set_text("copied".to_string());
+        assert_eq!(
+            clipboard.paste(PasteSource::Primary).as_deref(),
+            Some("copied")
+        );
+        clipboard.copy("pasted".to_string());
+        assert_eq!(handler.get_text().as_deref(), Some("pasted"));
+    }
+
+    #[test]
+    fn errors_are_logged_rather_than_raised() {
+        let clipboard = Clipboard::with_backend(Box::new(BrokenClipboard));
+        clipboard.copy("lost".to_string());
+        assert_eq!(clipboard.paste(PasteSource::Clipboard), None);
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ab_glyph::{Font, FontRef, PxScale, ScaleFont, VariableFont};
+use os_terminal::font::{ContentInfo, FontManager, Rasterized};
+
+use crate::options::FontOptions;
+
+// used when no font is configured.
+const DEFAULT_FONT: &[u8] = include_bytes!("../FiraCodeNotoSans.ttf");
+// how far the text can be zoomed, in points.
+const MIN_FONT_SIZE: f32 = 4.0;
+const MAX_FONT_SIZE: f32 = 72.0;
+
+/// A font in the fallback chain, with the scale that gives it the configured point size.
+#[derive(Clone)]
+struct Face {
+    font: FontRef<'static>,
+    scale: PxScale,
+}
+
+impl Face {
+    fn parse(data: &'static [u8], size: f32) -> Option<Self> {
+        let font = FontRef::try_from_slice(data).ok()?;
+        let scale = font.pt_to_px_scale(size)?;
+        Some(Self { font, scale })
+    }
+
+    fn resized(&self, size: f32) -> Self {
+        Self {
+            font: self.font.clone(),
+            // the font was scaled when it was loaded, so it has the units to be scaled again.
+            scale: self.font.pt_to_px_scale(size).unwrap(),
+        }
+    }
+}
+
+fn read_face(path: &Path, size: f32) -> Result<Face, Box<dyn Error>> {
+    let data = std::fs::read(path)
+        .map_err(|e| format!("could not read font {}: {}", path.display(), e))?;
+    // fonts are loaded once and used until the program exits, the glyphs borrow from the data.
+    let data = Box::leak(data.into_boxed_slice());
+    let face = Face::parse(data, size)
+        .ok_or_else(|| format!("{} is not a TrueType or OpenType font", path.display()))?;
+    Ok(face)
+}
+
+/// The configured font and its fallbacks, loaded once and shared by every font manager made
+/// from them. The fonts can be zoomed, in steps of a point from the configured size, and are
+/// scaled by the scale factor of the monitor the window is on.
+#[derive(Clone)]
+pub(crate) struct Fonts {
+    faces: Vec<Face>,
+    size: f32,
+    zoom: i32,
+    scale_factor: f32,
+}
+
+impl Fonts {
+    pub(crate) fn load(options: &FontOptions) -> Result<Self, Box<dyn Error>> {
+        if !(options.size.is_finite() && options.size > 0.0) {
+            return Err(format!("font size {} is not a positive number", options.size).into());
+        }
+        let primary = match &options.path {
+            Some(path) => read_face(path, options.size)?,
+            None => Face::parse(DEFAULT_FONT, options.size).expect("the default font is valid"),
+        };
+        let mut faces = vec![primary];
+        for path in &options.fallbacks {
+            faces.push(read_face(path, options.size)?);
+        }
+        Ok(Self {
+            faces,
+            size: options.size,
+            zoom: 0,
+            scale_factor: 1.0,
+        })
+    }
+
+    pub(crate) fn zoom(&self) -> i32 {
+        self.zoom
+    }
+
+    /// Returns the fonts zoomed to the given level, as close as they can get to it.
+    pub(crate) fn with_zoom(&self, zoom: i32) -> Self {
+        // a configured size outside of the limits can always be returned to.
+        let lowest = (MIN_FONT_SIZE.min(self.size) - self.size).ceil() as i32;
+        let highest = (MAX_FONT_SIZE.max(self.size) - self.size).floor() as i32;
+        let zoom = zoom.clamp(lowest, highest);
+        self.scaled(zoom, self.scale_factor)
+    }
+
+    /// Returns the fonts scaled for a monitor with the given scale factor, at the same zoom.
+    pub(crate) fn with_scale_factor(&self, scale_factor: f64) -> Self {
+        self.scaled(self.zoom, scale_factor as f32)
+    }
+
+    fn scaled(&self, zoom: i32, scale_factor: f32) -> Self {
+        let size = (self.size + zoom as f32) * scale_factor;
+        Self {
+            faces: self.faces.iter().map(|face| face.resized(size)).collect(),
+            size: self.size,
+            zoom,
+            scale_factor,
+        }
+    }
+
+    /// The size of a cell, in pixels.
+    pub(crate) fn cell_size(&self) -> (usize, usize) {
+        FallbackFont::new(self).size()
+    }
+}
+
+/// Where the zoom level is kept between sessions, `$XDG_STATE_HOME/os-terminal-ratatui/zoom` or
+/// `~/.local/state/os-terminal-ratatui/zoom`.
+fn zoom_path() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| Path::new(&home).join(".local").join("state"))
+        })?;
+    Some(state_home.join("os-terminal-ratatui").join("zoom"))
+}
+
+/// Returns the zoom level the last session ended with, none if it wasn't zoomed or the level
+/// can't be read.
+pub(crate) fn restore_zoom() -> i32 {
+    zoom_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|zoom| zoom.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+pub(crate) fn save_zoom(zoom: i32) -> io::Result<()> {
+    let path = zoom_path().ok_or(io::ErrorKind::NotFound)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, format!("{}\n", zoom))
+}
+
+/// A font manager which draws each character with the first font in the chain that has a
+/// glyph for it. Cells are sized by the primary font, the same way os-terminal's
+/// `TrueTypeFont` sizes them.
+pub(crate) struct FallbackFont {
+    faces: Vec<Face>,
+    raster_width: usize,
+    raster_height: usize,
+    base_line_offset: f32,
+    bitmap_cache: BTreeMap<ContentInfo, Vec<Vec<u8>>>,
+}
+
+impl FallbackFont {
+    pub(crate) fn new(fonts: &Fonts) -> Self {
+        let primary = &fonts.faces[0];
+        let scaled_font = primary.font.as_scaled(primary.scale);
+        let line_height = scaled_font.height();
+
+        Self {
+            faces: fonts.faces.clone(),
+            raster_width: (line_height / 2.0) as usize,
+            raster_height: line_height as usize,
+            base_line_offset: scaled_font.ascent(),
+            bitmap_cache: BTreeMap::new(),
+        }
+    }
+
+    fn draw(&mut self, info: &ContentInfo) -> Vec<Vec<u8>> {
+        // glyph 0 is the font's "missing glyph" box, which the primary font draws if none of
+        // the fonts have the character.
+        let index = self
+            .faces
+            .iter()
+            .position(|face| face.font.glyph_id(info.content).0 != 0)
+            .unwrap_or(0);
+        let face = &mut self.faces[index];
+
+        // only variable fonts have a weight axis, other fonts are drawn at their own weight.
+        let font_weight = if info.bold { 700.0 } else { 400.0 };
+        face.font.set_variation(b"wght", font_weight);
+
+        let glyph = face.font.glyph_id(info.content).with_scale(face.scale);
+
+        let actual_width = self.raster_width * if info.wide { 2 } else { 1 };
+        let mut letter_bitmap = vec![vec![0u8; actual_width]; self.raster_height];
+
+        if let Some(outline) = face.font.outline_glyph(glyph) {
+            let px_bounds = outline.px_bounds();
+            let x_offset = px_bounds.min.x as isize;
+            let y_offset = (self.base_line_offset + px_bounds.min.y) as isize;
+
+            outline.draw(|x, y, coverage| {
+                let x = x_offset + x as isize;
+                let y = y_offset + y as isize;
+                if (0..actual_width as isize).contains(&x)
+                    && (0..self.raster_height as isize).contains(&y)
+                {
+                    letter_bitmap[y as usize][x as usize] = (coverage * 255.0) as u8;
+                }
+            });
+        }
+
+        letter_bitmap
+    }
+}
+
+impl FontManager for FallbackFont {
+    fn size(&self) -> (usize, usize) {
+        (self.raster_width, self.raster_height)
+    }
+
+    fn rasterize(&mut self, info: ContentInfo) -> Rasterized<'_> {
+        if !self.bitmap_cache.contains_key(&info) {
+            let bitmap = self.draw(&info);
+            self.bitmap_cache.insert(info.clone(), bitmap);
+        }
+        Rasterized::Vec(&self.bitmap_cache[&info])
+    }
+}
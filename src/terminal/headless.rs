@@ -0,0 +1,297 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use os_terminal::Terminal;
+use ratatui::crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::prelude::CrosstermBackend;
+
+use crate::options::Options;
+use crate::terminal::clipboard::{Clipboard, MemoryClipboard, PasteSource};
+use crate::terminal::font::Fonts;
+use crate::terminal::input::InputDecoder;
+use crate::terminal::osc52::ClipboardFilter;
+use crate::terminal::overlay::{OverlayLayer, capture_overlays};
+use crate::terminal::png::write_png;
+use crate::terminal::sixel::SixelFilter;
+use crate::terminal::record::Recorder;
+use crate::terminal::{
+    Display, ScreenSize, TerminalWriter, VirtualBackend, create_recorder, create_terminal,
+    forward_pty_input, send_expired_input,
+};
+use crate::theme::Themes;
+use crate::tui::TuiApp;
+use crate::tui::crossterm;
+use crate::tui::screen::{Screen, ScreenEvent};
+
+/// One line of a headless script.
+///
+/// ```text
+/// # comments and blank lines are ignored
+/// size 800 600          resize the display, in pixels
+/// key right             send a key, optionally with ctrl+, alt+ or shift+ prefixes
+/// text hello            send each character as a key
+/// wait 250              let the given number of milliseconds pass
+/// snapshot tab1.png     write the display as it currently looks to a PNG file
+/// paste                 paste what the application last copied
+/// close                 ask the application to close, as the window's close button would
+/// ```
+enum Command {
+    Size(usize, usize),
+    Key(KeyEvent),
+    Wait(Duration),
+    Snapshot(PathBuf),
+    Paste,
+    Close,
+}
+
+fn parse_key(spec: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = spec;
+    while let Some((modifier, rest)) = name.split_once('+').filter(|(_, rest)| !rest.is_empty()) {
+        modifiers |= match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        name = rest;
+    }
+
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match name.to_ascii_lowercase().as_str() {
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            function => KeyCode::F(function.strip_prefix('f')?.parse().ok()?),
+        },
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+fn parse_script(script: &str) -> Result<VecDeque<Command>, Box<dyn Error>> {
+    let mut commands = VecDeque::new();
+    for (number, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        let invalid = || format!("line {}: invalid command '{}'", number + 1, line);
+
+        match command {
+            "size" => {
+                let (width, height) = argument.split_once(' ').ok_or_else(invalid)?;
+                let width = width.trim().parse().map_err(|_| invalid())?;
+                let height = height.trim().parse().map_err(|_| invalid())?;
+                commands.push_back(Command::Size(width, height));
+            }
+            "key" => commands.push_back(Command::Key(parse_key(argument).ok_or_else(invalid)?)),
+            "text" => commands.extend(
+                argument
+                    .chars()
+                    .map(|c| Command::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))),
+            ),
+            "wait" => {
+                let millis = argument.parse().map_err(|_| invalid())?;
+                commands.push_back(Command::Wait(Duration::from_millis(millis)));
+            }
+            "snapshot" if !argument.is_empty() => {
+                commands.push_back(Command::Snapshot(PathBuf::from(argument)))
+            }
+            "paste" if argument.is_empty() => commands.push_back(Command::Paste),
+            "close" if argument.is_empty() => commands.push_back(Command::Close),
+            _ => return Err(invalid().into()),
+        }
+    }
+    Ok(commands)
+}
+
+/// A screen which takes its input from a script instead of a window. Because the TUI always
+/// draws before it polls for input, every command sees the frame produced by the previous one.
+struct HeadlessScreen {
+    commands: RefCell<VecDeque<Command>>,
+    input_rx: Receiver<ScreenEvent>,
+    input_tx: Sender<ScreenEvent>,
+    decoder: Arc<Mutex<InputDecoder>>,
+    terminal: Arc<Mutex<Terminal<Display>>>,
+    display: RefCell<Display>,
+    size: Arc<Mutex<ScreenSize>>,
+    recorder: Option<Arc<Recorder>>,
+    overlays: Arc<Mutex<OverlayLayer>>,
+    clipboard: Clipboard,
+}
+
+impl HeadlessScreen {
+    fn resize(&self, width: usize, height: usize) -> (u16, u16) {
+        let display = Display::new(width, height);
+        *self.display.borrow_mut() = display.clone();
+
+        let mut terminal = self.terminal.lock().unwrap();
+        terminal.set_display(display);
+        (terminal.columns() as u16, terminal.rows() as u16)
+    }
+
+    fn snapshot(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        self.terminal.lock().unwrap().flush();
+
+        let display = self.display.borrow();
+        let file = File::create(path)
+            .map_err(|e| format!("could not create snapshot {}: {}", path.display(), e))?;
+        let mut pixels = display
+            .buffer
+            .iter()
+            .map(|pixel| pixel.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+        let overlays = self.overlays.lock().unwrap();
+        overlays.composite(&mut pixels, display.width, &(0..display.height));
+        write_png(BufWriter::new(file), display.width, display.height, pixels.into_iter())?;
+        Ok(())
+    }
+
+    fn next_event(&self) -> Result<Option<ScreenEvent>, Box<dyn Error>> {
+        // replies from the terminal, such as cursor position reports, come before the script.
+        send_expired_input(&mut self.decoder.lock().unwrap(), &self.input_tx);
+        if let Ok(event) = self.input_rx.try_recv() {
+            return Ok(Some(event));
+        }
+
+        loop {
+            let Some(command) = self.commands.borrow_mut().pop_front() else {
+                return Err("the headless script ended before the application quit".into());
+            };
+            match command {
+                Command::Size(width, height) => {
+                    let (cols, rows) = self.resize(width, height);
+                    return Ok(Some(Event::Resize(cols, rows).into()));
+                }
+                Command::Key(key) => return Ok(Some(Event::Key(key).into())),
+                Command::Wait(duration) => {
+                    std::thread::sleep(duration);
+                    return Ok(None);
+                }
+                Command::Snapshot(path) => self.snapshot(&path)?,
+                Command::Paste => {
+                    let text = self.clipboard.paste(PasteSource::Clipboard).unwrap_or_default();
+                    return Ok(Some(Event::Paste(text).into()));
+                }
+                Command::Close => return Ok(Some(ScreenEvent::CloseRequested)),
+            }
+        }
+    }
+}
+
+impl<W: Write> Screen<W> for HeadlessScreen {
+
+    type Backend = VirtualBackend<W>;
+
+    fn poll_and_read(&self, _timeout: Duration) -> Result<Option<ScreenEvent>,Box<dyn Error>> {
+        let event = self.next_event()?;
+        if let Some(recorder) = &self.recorder
+            && let Some(ScreenEvent::Input(event)) = &event
+        {
+            recorder.input(event)?;
+        }
+        Ok(event)
+    }
+
+    fn enable_raw_mode(&self) -> Result<(),Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn disable_raw_mode(&self) -> Result<(),Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn create_backend(&self, stdout: W) -> Self::Backend {
+        // snapshots are taken on this thread, so nothing has to be told about new overlays.
+        let pending_draw = Arc::new(AtomicBool::new(false));
+        capture_overlays(self.size.clone(), self.overlays.clone(), pending_draw);
+        let inner = CrosstermBackend::new(stdout);
+        VirtualBackend::new(inner, self.size.clone())
+    }
+
+    fn resize(&self, cols: u16, rows: u16) {
+        let mut size = self.size.lock().unwrap();
+        size.cols = cols;
+        size.rows = rows;
+    }
+
+}
+
+/// Runs the application through os-terminal into an offscreen display, driven by the given
+/// script, so that what the window would show can be captured without a display server. The
+/// render mode in the options is ignored, headless rendering always goes through os-terminal.
+pub fn run_headless<A: TuiApp>(
+    app: A,
+    options: Options,
+    script_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let script = std::fs::read_to_string(script_path)
+        .map_err(|e| format!("could not read script {}: {}", script_path.display(), e))?;
+    let commands = parse_script(&script)?;
+
+    let display = Display::default();
+    let fonts = Fonts::load(&options.font)?;
+    let theme = Themes::new(&options)?.current().palette();
+    let mut terminal = create_terminal(display.clone(), &fonts);
+    terminal.set_custom_color_scheme(&theme);
+    terminal.set_auto_flush(false);
+    // a script run must not depend on, or change, what's on the system clipboard.
+    let clipboard = Clipboard::with_backend(Box::<MemoryClipboard>::default());
+    terminal.set_clipboard(Box::new(clipboard.clone()));
+
+    let (input_tx, input_rx) = channel::<ScreenEvent>();
+    let decoder = forward_pty_input(&mut terminal, input_tx.clone());
+
+    let recorder = create_recorder(&options, terminal.columns(), terminal.rows())?;
+    let size = ScreenSize::new(terminal.columns(), terminal.rows(), fonts.cell_size());
+    let size = Arc::new(Mutex::new(size));
+    let terminal = Arc::new(Mutex::new(terminal));
+
+    let overlays = Arc::new(Mutex::new(OverlayLayer::default()));
+    let writer = TerminalWriter::new(
+        terminal.clone(),
+        Arc::new(AtomicBool::new(false)),
+        recorder.clone(),
+        Some(SixelFilter::new(size.clone(), overlays.clone())),
+        Some(ClipboardFilter::new(clipboard.clone(), None)),
+    );
+    let screen = HeadlessScreen {
+        commands: RefCell::new(commands),
+        input_rx,
+        input_tx,
+        decoder,
+        terminal,
+        display: RefCell::new(display),
+        size,
+        recorder,
+        overlays,
+        clipboard,
+    };
+
+    crossterm::run(app, &options, writer, screen)
+}
@@ -0,0 +1,270 @@
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{Event, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use terminput::{Encoding, Event as TermInputEvent};
+use terminput_crossterm::{to_crossterm, to_terminput};
+
+use crate::terminal::keyboard::without_event_type;
+use crate::terminal::modes::{Modes, MouseTracking};
+
+const ESC: u8 = 0x1b;
+// the longest key sequence terminput writes is well under this.
+const MAX_KEY_SEQUENCE: usize = 32;
+// the legacy mouse encoding offsets every value by 32 and stores it in a single byte.
+const MAX_LEGACY_MOUSE_COORDINATE: u16 = 255 - 32;
+/// How long the start of an escape sequence is held for the rest of it. An escape with nothing
+/// after it by then was the escape key.
+const ESCAPE_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Input which could not be turned into an event. The bytes are discarded and decoding carries
+/// on with whatever follows them.
+#[derive(Debug)]
+pub(crate) struct MalformedInput(Vec<u8>);
+
+impl fmt::Display for MalformedInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed input \"{}\"", self.0.escape_ascii())
+    }
+}
+
+impl Error for MalformedInput {}
+
+/// Turns the bytes a terminal writes as input back into events. Escape sequences may be split
+/// across calls, so anything which could still become a valid event is kept until the rest of
+/// it arrives, or until [`expire`](Self::expire) decides it isn't coming.
+pub(crate) struct InputDecoder {
+    buffer: Vec<u8>,
+    /// When the bytes in the buffer started waiting for the rest of their sequence.
+    waiting_since: Option<Instant>,
+}
+
+impl InputDecoder {
+    pub(crate) fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            waiting_since: None,
+        }
+    }
+
+    /// Returns every event completed by the given bytes, in order.
+    pub(crate) fn decode(&mut self, data: &[u8]) -> Vec<Result<Event, MalformedInput>> {
+        let mut events = Vec::new();
+        for byte in data {
+            self.buffer.push(*byte);
+
+            // terminput reads a lone escape as the escape key, which is only right if nothing
+            // follows it, here or in the next call.
+            if self.buffer == [ESC] {
+                continue;
+            }
+
+            match TermInputEvent::parse_from(&self.buffer) {
+                Ok(None) => {}
+                Ok(Some(event)) => {
+                    let bytes = std::mem::take(&mut self.buffer);
+                    events.push(to_crossterm(event).map_err(|_| MalformedInput(bytes)));
+                }
+                Err(_) => events.push(Err(MalformedInput(std::mem::take(&mut self.buffer)))),
+            }
+        }
+        self.waiting_since = match self.waiting_since {
+            _ if self.buffer.is_empty() => None,
+            Some(since) => Some(since),
+            None => Some(Instant::now()),
+        };
+        events
+    }
+
+    /// Gives up on a sequence which has waited longer than [`ESCAPE_TIMEOUT`] for the rest of
+    /// it. A lone escape becomes the escape key, anything else is malformed.
+    pub(crate) fn expire(&mut self, now: Instant) -> Option<Result<Event, MalformedInput>> {
+        let since = self.waiting_since?;
+        if now.duration_since(since) < ESCAPE_TIMEOUT {
+            return None;
+        }
+        self.waiting_since = None;
+        let bytes = std::mem::take(&mut self.buffer);
+        match TermInputEvent::parse_from(&bytes) {
+            Ok(Some(event)) => Some(to_crossterm(event).map_err(|_| MalformedInput(bytes))),
+            _ => Some(Err(MalformedInput(bytes))),
+        }
+    }
+}
+
+/// Turns an event back into the bytes a terminal would send for it, taking into account the
+/// modes the program has set. Returns nothing for events the program didn't ask for, or which
+/// have no encoding, such as key releases.
+pub(crate) fn encode_input(event: &Event, modes: Modes) -> Option<Vec<u8>> {
+    match event {
+        Event::Key(key) => {
+            let key = without_event_type(*key)?;
+            let mut buf = [0; MAX_KEY_SEQUENCE];
+            let event = to_terminput(Event::Key(key)).ok()?;
+            let len = event.encode(&mut buf, Encoding::Xterm).ok()?;
+            let mut bytes = buf[..len].to_vec();
+            // in application mode, unmodified cursor keys use SS3 instead of CSI.
+            if modes.application_cursor
+                && let [ESC, intro @ b'[', b'A' | b'B' | b'C' | b'D' | b'H' | b'F'] =
+                    bytes.as_mut_slice()
+            {
+                *intro = b'O';
+            }
+            Some(bytes)
+        }
+        Event::Mouse(mouse) => encode_mouse(mouse, modes),
+        Event::Paste(text) if modes.bracketed_paste => {
+            Some(format!("\x1b[200~{}\x1b[201~", text).into_bytes())
+        }
+        Event::Paste(text) => Some(text.as_bytes().to_vec()),
+        _ => None,
+    }
+}
+
+fn encode_mouse(event: &MouseEvent, modes: Modes) -> Option<Vec<u8>> {
+    let button_code = |button| match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+    };
+    let (mut code, release) = match event.kind {
+        MouseEventKind::Down(button) => (button_code(button), false),
+        MouseEventKind::Up(button) => (button_code(button), true),
+        MouseEventKind::Drag(button) if modes.mouse >= MouseTracking::Drag => {
+            (button_code(button) + 32, false)
+        }
+        MouseEventKind::Moved if modes.mouse == MouseTracking::Motion => (3 + 32, false),
+        MouseEventKind::ScrollUp => (64, false),
+        MouseEventKind::ScrollDown => (65, false),
+        MouseEventKind::ScrollLeft => (66, false),
+        MouseEventKind::ScrollRight => (67, false),
+        _ => return None,
+    };
+    if modes.mouse == MouseTracking::Off {
+        return None;
+    }
+
+    if event.modifiers.contains(KeyModifiers::SHIFT) {
+        code += 4;
+    }
+    if event.modifiers.contains(KeyModifiers::ALT) {
+        code += 8;
+    }
+    if event.modifiers.contains(KeyModifiers::CONTROL) {
+        code += 16;
+    }
+
+    let (column, row) = (event.column + 1, event.row + 1);
+    if modes.sgr_mouse {
+        let suffix = if release { 'm' } else { 'M' };
+        return Some(format!("\x1b[<{};{};{}{}", code, column, row, suffix).into_bytes());
+    }
+
+    // the legacy encoding doesn't say which button was released, and can't reach far cells.
+    if column > MAX_LEGACY_MOUSE_COORDINATE || row > MAX_LEGACY_MOUSE_COORDINATE {
+        return None;
+    }
+    if release {
+        code = (code & !0b11) | 3;
+    }
+    Some(vec![ESC, b'[', b'M', 32 + code, 32 + column as u8, 32 + row as u8])
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::{KeyCode, KeyEvent};
+
+    use super::*;
+
+    /// Input as a terminal writes it, each entry a single event.
+    const CORPUS: &[&[u8]] = &[
+        b"a",
+        "é".as_bytes(),
+        "日".as_bytes(),
+        b"\r",
+        b"\x7f",
+        b"\x1b[A",
+        b"\x1b[1;5C",
+        b"\x1bOP",
+        b"\x1b[15~",
+        b"\x1b[3;2~",
+        b"\x1b[<0;10;5M",
+        b"\x1b[<0;10;5m",
+        b"\x1b[M !!",
+        b"\x1b[I",
+        b"\x1bx",
+    ];
+
+    fn decode_chunks(chunks: &[&[u8]]) -> Vec<Event> {
+        let mut decoder = InputDecoder::new();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            for event in decoder.decode(chunk) {
+                events.push(event.unwrap());
+            }
+        }
+        assert!(decoder.buffer.is_empty(), "{:?} left input behind", chunks);
+        events
+    }
+
+    #[test]
+    fn every_entry_is_one_event() {
+        for entry in CORPUS {
+            assert_eq!(decode_chunks(&[entry]).len(), 1, "{}", entry.escape_ascii());
+        }
+    }
+
+    #[test]
+    fn splits_decode_the_same() {
+        for entry in CORPUS {
+            let whole = decode_chunks(&[entry]);
+            for split in 0..=entry.len() {
+                let (first, second) = entry.split_at(split);
+                assert_eq!(decode_chunks(&[first, second]), whole, "{}", entry.escape_ascii());
+            }
+        }
+    }
+
+    #[test]
+    fn concatenated_input_splits_decode_the_same() {
+        let input = CORPUS.concat();
+        let whole = decode_chunks(&[&input]);
+        assert_eq!(whole.len(), CORPUS.len());
+        for first in 0..=input.len() {
+            for second in first..=input.len() {
+                let chunks = [&input[..first], &input[first..second], &input[second..]];
+                assert_eq!(decode_chunks(&chunks), whole, "split at {} and {}", first, second);
+            }
+        }
+        let bytes = input.chunks(1).collect::<Vec<_>>();
+        assert_eq!(decode_chunks(&bytes), whole);
+    }
+
+    #[test]
+    fn escape_waits_for_the_next_chunk() {
+        let mut decoder = InputDecoder::new();
+        assert!(decoder.decode(b"\x1b").is_empty());
+        let events = decoder.decode(b"[B").into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(events, [Event::Key(KeyEvent::from(KeyCode::Down))]);
+        assert!(decoder.expire(Instant::now() + ESCAPE_TIMEOUT).is_none());
+    }
+
+    #[test]
+    fn lone_escape_expires_as_the_escape_key() {
+        let mut decoder = InputDecoder::new();
+        assert!(decoder.decode(b"\x1b").is_empty());
+        assert!(decoder.expire(Instant::now()).is_none());
+        let event = decoder.expire(Instant::now() + ESCAPE_TIMEOUT).unwrap().unwrap();
+        assert_eq!(event, Event::Key(KeyEvent::from(KeyCode::Esc)));
+        assert!(decoder.expire(Instant::now() + ESCAPE_TIMEOUT).is_none());
+    }
+
+    #[test]
+    fn partial_sequence_expires_as_malformed() {
+        let mut decoder = InputDecoder::new();
+        assert!(decoder.decode(b"\x1b[1;").is_empty());
+        assert!(decoder.expire(Instant::now() + ESCAPE_TIMEOUT).unwrap().is_err());
+        assert_eq!(decoder.decode(b"q").len(), 1);
+    }
+}
@@ -0,0 +1,319 @@
+use ratatui::crossterm::event::{
+    KeyCode, KeyEvent, KeyEventKind, KeyEventState, KeyModifiers, MediaKeyCode, ModifierKeyCode,
+};
+use winit::event::{ElementState, KeyEvent as WinitKeyEvent};
+use winit::keyboard::{Key, KeyLocation, ModifiersState, NamedKey};
+
+/// Named keys which translate to a single crossterm key code regardless of where they are on
+/// the keyboard. Modifier keys and function keys are handled separately.
+const NAMED_KEYS: &[(NamedKey, KeyCode)] = &[
+    (NamedKey::Enter, KeyCode::Enter),
+    (NamedKey::Tab, KeyCode::Tab),
+    (NamedKey::Space, KeyCode::Char(' ')),
+    (NamedKey::Backspace, KeyCode::Backspace),
+    (NamedKey::Escape, KeyCode::Esc),
+    (NamedKey::Delete, KeyCode::Delete),
+    (NamedKey::Insert, KeyCode::Insert),
+    (NamedKey::Home, KeyCode::Home),
+    (NamedKey::End, KeyCode::End),
+    (NamedKey::PageUp, KeyCode::PageUp),
+    (NamedKey::PageDown, KeyCode::PageDown),
+    (NamedKey::ArrowUp, KeyCode::Up),
+    (NamedKey::ArrowDown, KeyCode::Down),
+    (NamedKey::ArrowLeft, KeyCode::Left),
+    (NamedKey::ArrowRight, KeyCode::Right),
+    (NamedKey::CapsLock, KeyCode::CapsLock),
+    (NamedKey::ScrollLock, KeyCode::ScrollLock),
+    (NamedKey::NumLock, KeyCode::NumLock),
+    (NamedKey::PrintScreen, KeyCode::PrintScreen),
+    (NamedKey::Pause, KeyCode::Pause),
+    (NamedKey::ContextMenu, KeyCode::Menu),
+    (NamedKey::MediaPlay, KeyCode::Media(MediaKeyCode::Play)),
+    (NamedKey::MediaPause, KeyCode::Media(MediaKeyCode::Pause)),
+    (NamedKey::MediaPlayPause, KeyCode::Media(MediaKeyCode::PlayPause)),
+    (NamedKey::MediaStop, KeyCode::Media(MediaKeyCode::Stop)),
+    (NamedKey::MediaFastForward, KeyCode::Media(MediaKeyCode::FastForward)),
+    (NamedKey::MediaRewind, KeyCode::Media(MediaKeyCode::Rewind)),
+    (NamedKey::MediaTrackNext, KeyCode::Media(MediaKeyCode::TrackNext)),
+    (NamedKey::MediaTrackPrevious, KeyCode::Media(MediaKeyCode::TrackPrevious)),
+    (NamedKey::MediaRecord, KeyCode::Media(MediaKeyCode::Record)),
+    (NamedKey::AudioVolumeDown, KeyCode::Media(MediaKeyCode::LowerVolume)),
+    (NamedKey::AudioVolumeUp, KeyCode::Media(MediaKeyCode::RaiseVolume)),
+    (NamedKey::AudioVolumeMute, KeyCode::Media(MediaKeyCode::MuteVolume)),
+];
+
+const FUNCTION_KEYS: &[NamedKey] = &[
+    NamedKey::F1, NamedKey::F2, NamedKey::F3, NamedKey::F4, NamedKey::F5, NamedKey::F6,
+    NamedKey::F7, NamedKey::F8, NamedKey::F9, NamedKey::F10, NamedKey::F11, NamedKey::F12,
+    NamedKey::F13, NamedKey::F14, NamedKey::F15, NamedKey::F16, NamedKey::F17, NamedKey::F18,
+    NamedKey::F19, NamedKey::F20, NamedKey::F21, NamedKey::F22, NamedKey::F23, NamedKey::F24,
+];
+
+/// Modifier keys, with the left hand and right hand variants.
+const MODIFIER_KEYS: &[(NamedKey, ModifierKeyCode, ModifierKeyCode)] = &[
+    (NamedKey::Shift, ModifierKeyCode::LeftShift, ModifierKeyCode::RightShift),
+    (NamedKey::Control, ModifierKeyCode::LeftControl, ModifierKeyCode::RightControl),
+    (NamedKey::Alt, ModifierKeyCode::LeftAlt, ModifierKeyCode::RightAlt),
+    (NamedKey::Super, ModifierKeyCode::LeftSuper, ModifierKeyCode::RightSuper),
+    (NamedKey::Hyper, ModifierKeyCode::LeftHyper, ModifierKeyCode::RightHyper),
+    (NamedKey::Meta, ModifierKeyCode::LeftMeta, ModifierKeyCode::RightMeta),
+    (NamedKey::AltGraph, ModifierKeyCode::IsoLevel3Shift, ModifierKeyCode::IsoLevel3Shift),
+];
+
+/// Keys the window handles itself rather than passing them on to the application.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Shortcut {
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    NextTheme,
+    Copy,
+    Paste,
+}
+
+/// The shortcuts, which are all the key together with control, and with shift as well where
+/// the key is a letter. Letters match in either case, since caps lock changes the case without
+/// shift being held, and control with a lower case letter belongs to the application.
+const SHORTCUTS: &[(&str, bool, Shortcut)] = &[
+    ("+", false, Shortcut::ZoomIn),
+    ("=", false, Shortcut::ZoomIn),
+    ("-", false, Shortcut::ZoomOut),
+    ("0", false, Shortcut::ResetZoom),
+    ("t", true, Shortcut::NextTheme),
+    ("c", true, Shortcut::Copy),
+    ("v", true, Shortcut::Paste),
+];
+
+pub(super) fn to_key_modifiers(state: ModifiersState) -> KeyModifiers {
+    let mut modifiers = KeyModifiers::NONE;
+    modifiers.set(KeyModifiers::SHIFT, state.shift_key());
+    modifiers.set(KeyModifiers::CONTROL, state.control_key());
+    modifiers.set(KeyModifiers::ALT, state.alt_key());
+    modifiers.set(KeyModifiers::SUPER, state.super_key());
+    modifiers
+}
+
+fn named_key_code(key: NamedKey, location: KeyLocation) -> Option<KeyCode> {
+    if let Some((_, code)) = NAMED_KEYS.iter().find(|(named, _)| *named == key) {
+        return Some(*code);
+    }
+    if let Some(index) = FUNCTION_KEYS.iter().position(|named| *named == key) {
+        return Some(KeyCode::F(index as u8 + 1));
+    }
+    MODIFIER_KEYS
+        .iter()
+        .find(|(named, _, _)| *named == key)
+        .map(|(_, left, right)| match location {
+            KeyLocation::Right => KeyCode::Modifier(*right),
+            _ => KeyCode::Modifier(*left),
+        })
+}
+
+/// Translates a key event from the window into the key event a terminal would report for it.
+///
+/// The logical key is used, so the result follows the active keyboard layout. Keys without a
+/// crossterm equivalent, such as dead keys, are dropped; text they compose arrives through the
+/// IME instead.
+pub(super) fn translate_key(event: &WinitKeyEvent, modifiers: ModifiersState) -> Option<KeyEvent> {
+    let state = (event.state, event.repeat);
+    translate(&event.logical_key, event.location, state, modifiers)
+}
+
+/// Does the work of [`translate_key`] on the parts of the event it needs, which unlike the
+/// event itself can be made outside of winit.
+fn translate(
+    logical_key: &Key,
+    location: KeyLocation,
+    (state, repeat): (ElementState, bool),
+    modifiers: ModifiersState,
+) -> Option<KeyEvent> {
+    let modifiers = to_key_modifiers(modifiers);
+    let code = match logical_key {
+        Key::Named(key) => named_key_code(*key, location)?,
+        Key::Character(text) => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return None,
+            }
+        }
+        Key::Unidentified(_) | Key::Dead(_) => return None,
+    };
+
+    // crossterm reports shift+tab as a distinct key.
+    let code = match code {
+        KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+        code => code,
+    };
+    let kind = match (state, repeat) {
+        (ElementState::Pressed, false) => KeyEventKind::Press,
+        (ElementState::Pressed, true) => KeyEventKind::Repeat,
+        (ElementState::Released, _) => KeyEventKind::Release,
+    };
+    let state = if location == KeyLocation::Numpad {
+        KeyEventState::KEYPAD
+    } else {
+        KeyEventState::NONE
+    };
+
+    Some(KeyEvent {
+        code,
+        modifiers,
+        kind,
+        state,
+    })
+}
+
+/// Returns the key as a program which hasn't asked for key event types gets it, repeats come
+/// as presses and releases not at all.
+pub(super) fn without_event_type(mut key: KeyEvent) -> Option<KeyEvent> {
+    match key.kind {
+        KeyEventKind::Press => {}
+        KeyEventKind::Repeat => key.kind = KeyEventKind::Press,
+        KeyEventKind::Release => return None,
+    }
+    Some(key)
+}
+
+/// Returns the shortcut a key event belongs to. Releases match as well, so that the application
+/// doesn't see the release of a key it never saw pressed.
+pub(super) fn shortcut(event: &WinitKeyEvent, modifiers: ModifiersState) -> Option<Shortcut> {
+    find_shortcut(&event.logical_key, modifiers)
+}
+
+fn find_shortcut(logical_key: &Key, modifiers: ModifiersState) -> Option<Shortcut> {
+    if !modifiers.control_key() || modifiers.alt_key() || modifiers.super_key() {
+        return None;
+    }
+    let Key::Character(text) = logical_key else {
+        return None;
+    };
+    // shift is allowed with the other keys, on most layouts plus is a shifted key.
+    SHORTCUTS
+        .iter()
+        .find(|(key, shift, _)| key.eq_ignore_ascii_case(text) && (!shift || modifiers.shift_key()))
+        .map(|(_, _, shortcut)| *shortcut)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESS: (ElementState, bool) = (ElementState::Pressed, false);
+    const REPEAT: (ElementState, bool) = (ElementState::Pressed, true);
+    const RELEASE: (ElementState, bool) = (ElementState::Released, false);
+
+    fn character(text: &str) -> Key {
+        Key::Character(text.into())
+    }
+
+    #[test]
+    fn translates_keys() {
+        let none = ModifiersState::empty();
+        let shift = ModifiersState::SHIFT;
+        let ctrl = ModifiersState::CONTROL;
+        let alt_super = ModifiersState::ALT | ModifiersState::SUPER;
+        let standard = KeyLocation::Standard;
+        let numpad = KeyLocation::Numpad;
+        #[rustfmt::skip]
+        let cases = [
+            // named keys
+            (Key::Named(NamedKey::Enter), standard, PRESS, none, Some(KeyCode::Enter)),
+            (Key::Named(NamedKey::Escape), standard, PRESS, none, Some(KeyCode::Esc)),
+            (Key::Named(NamedKey::Space), standard, PRESS, none, Some(KeyCode::Char(' '))),
+            (Key::Named(NamedKey::ArrowUp), standard, PRESS, ctrl, Some(KeyCode::Up)),
+            (Key::Named(NamedKey::F5), standard, PRESS, none, Some(KeyCode::F(5))),
+            (Key::Named(NamedKey::F24), standard, PRESS, none, Some(KeyCode::F(24))),
+            (Key::Named(NamedKey::MediaPlayPause), standard, PRESS, none,
+                Some(KeyCode::Media(MediaKeyCode::PlayPause))),
+            (Key::Named(NamedKey::Tab), standard, PRESS, none, Some(KeyCode::Tab)),
+            (Key::Named(NamedKey::Tab), standard, PRESS, shift, Some(KeyCode::BackTab)),
+            // modifier keys, by the side of the keyboard they are on
+            (Key::Named(NamedKey::Shift), KeyLocation::Left, PRESS, shift,
+                Some(KeyCode::Modifier(ModifierKeyCode::LeftShift))),
+            (Key::Named(NamedKey::Control), KeyLocation::Right, PRESS, ctrl,
+                Some(KeyCode::Modifier(ModifierKeyCode::RightControl))),
+            // characters, as the layout produces them
+            (character("a"), standard, PRESS, none, Some(KeyCode::Char('a'))),
+            (character("A"), standard, PRESS, shift, Some(KeyCode::Char('A'))),
+            (character("é"), standard, PRESS, alt_super, Some(KeyCode::Char('é'))),
+            (character("ab"), standard, PRESS, none, None),
+            (Key::Dead(Some('`')), standard, PRESS, none, None),
+            // keypad
+            (character("5"), numpad, PRESS, none, Some(KeyCode::Char('5'))),
+            (Key::Named(NamedKey::Enter), numpad, PRESS, none, Some(KeyCode::Enter)),
+            // repeats and releases
+            (character("a"), standard, REPEAT, none, Some(KeyCode::Char('a'))),
+            (character("a"), standard, RELEASE, none, Some(KeyCode::Char('a'))),
+        ];
+
+        for (key, location, state, modifiers, code) in cases {
+            let event = translate(&key, location, state, modifiers);
+            assert_eq!(event.map(|event| event.code), code, "{:?}", key);
+            let Some(event) = event else {
+                continue;
+            };
+            assert_eq!(event.modifiers, to_key_modifiers(modifiers), "{:?}", key);
+            let kind = match state {
+                PRESS => KeyEventKind::Press,
+                REPEAT => KeyEventKind::Repeat,
+                _ => KeyEventKind::Release,
+            };
+            assert_eq!(event.kind, kind, "{:?}", key);
+            let keypad = event.state.contains(KeyEventState::KEYPAD);
+            assert_eq!(keypad, location == numpad, "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn converts_modifiers() {
+        let state = ModifiersState::SHIFT | ModifiersState::ALT | ModifiersState::SUPER;
+        let modifiers = KeyModifiers::SHIFT | KeyModifiers::ALT | KeyModifiers::SUPER;
+        assert_eq!(to_key_modifiers(state), modifiers);
+        assert_eq!(to_key_modifiers(ModifiersState::empty()), KeyModifiers::NONE);
+    }
+
+    #[test]
+    fn finds_shortcuts() {
+        let ctrl = ModifiersState::CONTROL;
+        let ctrl_shift = ModifiersState::CONTROL | ModifiersState::SHIFT;
+        #[rustfmt::skip]
+        let cases = [
+            ("T", ctrl_shift, Some(Shortcut::NextTheme)),
+            ("c", ctrl_shift, Some(Shortcut::Copy)),
+            ("V", ctrl_shift, Some(Shortcut::Paste)),
+            // caps lock turns the letter upper case without shift.
+            ("T", ctrl, None),
+            ("c", ctrl, None),
+            ("V", ModifiersState::SHIFT, None),
+            ("C", ctrl_shift | ModifiersState::ALT, None),
+            ("C", ctrl_shift | ModifiersState::SUPER, None),
+            ("+", ctrl_shift, Some(Shortcut::ZoomIn)),
+            ("=", ctrl, Some(Shortcut::ZoomIn)),
+            ("-", ctrl, Some(Shortcut::ZoomOut)),
+            ("0", ctrl, Some(Shortcut::ResetZoom)),
+            ("0", ModifiersState::empty(), None),
+            ("x", ctrl_shift, None),
+        ];
+        for (key, modifiers, expected) in cases {
+            assert_eq!(
+                find_shortcut(&character(key), modifiers),
+                expected,
+                "{key} {modifiers:?}"
+            );
+        }
+        assert_eq!(
+            find_shortcut(&Key::Named(NamedKey::Enter), ctrl_shift),
+            None
+        );
+    }
+
+    #[test]
+    fn keys_without_event_types_are_presses() {
+        let key = |kind| KeyEvent::new_with_kind(KeyCode::Char('a'), KeyModifiers::NONE, kind);
+        let press = Some(key(KeyEventKind::Press));
+        assert_eq!(without_event_type(key(KeyEventKind::Press)), press);
+        assert_eq!(without_event_type(key(KeyEventKind::Repeat)), press);
+        assert_eq!(without_event_type(key(KeyEventKind::Release)), None);
+    }
+}
@@ -0,0 +1,2075 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::num::NonZeroU32;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use os_terminal::font::{ContentInfo, FontManager, Rasterized};
+use os_terminal::{DrawTarget, Rgb, Terminal};
+use softbuffer::{Context, Rect, Surface};
+use winit::application::ApplicationHandler;
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, Ime, MouseButton, StartCause, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop, EventLoopProxy};
+use winit::keyboard::ModifiersState;
+use winit::window::{ImePurpose, Window, WindowAttributes, WindowId};
+
+use crate::options::Options;
+use crate::theme::{Theme, Themes};
+use crate::tui::TuiApp;
+use crate::tui::crossterm;
+use std::io::{self, Write};
+use ratatui::crossterm::event::{Event, KeyEvent};
+use ratatui::prelude::{Backend, CrosstermBackend};
+use ratatui::buffer::Cell;
+use ratatui::layout::{Position, Size};
+use ratatui::backend::WindowSize;
+use ratatui::style::{Color, Modifier};
+use ratatui::text::Span;
+use crate::tui::screen::{Screen, ScreenEvent};
+use clipboard::{Clipboard, PasteSource};
+use font::{FallbackFont, Fonts, restore_zoom, save_zoom};
+use input::{InputDecoder, MalformedInput};
+use keyboard::{Shortcut, shortcut, translate_key, without_event_type};
+use modes::{ModeScanner, MouseTracking};
+use mouse::MouseTracker;
+use osc52::ClipboardFilter;
+use overlay::{OverlayLayer, capture_overlays, commit_overlays};
+use preedit::draw_preedit;
+use record::Recorder;
+use selection::{ScreenText, Selector};
+use sixel::SixelFilter;
+
+mod clipboard;
+mod font;
+mod headless;
+mod input;
+mod keyboard;
+mod modes;
+mod mouse;
+mod osc52;
+mod overlay;
+mod png;
+mod play;
+mod preedit;
+#[cfg(target_os = "linux")]
+mod pty;
+mod record;
+#[cfg(target_os = "linux")]
+mod report;
+mod selection;
+mod sixel;
+
+pub use headless::run_headless;
+pub use overlay::{PixelOverlay, Pixels};
+pub use play::run_playback;
+#[cfg(target_os = "linux")]
+pub use pty::run_command;
+
+const DISPLAY_SIZE: (usize, usize) = (1024, 768);
+// how many lines scrolled off the top of the screen are kept.
+const HISTORY_SIZE: usize = 1000;
+// how many lines a step of the mouse wheel scrolls the history by.
+const SCROLL_SPEED: usize = 5;
+// how long the TUI thread gets to stop after the window has closed.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How the TUI gets from ratatui's buffer to pixels in the window.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Cells are written as ANSI escape sequences and rendered by os-terminal.
+    Terminal,
+    /// Cells are rasterized straight into the display, without going through os-terminal.
+    Pixel,
+}
+
+/// Where os-terminal reports what it doesn't handle, and the window reports problems with the
+/// system which shouldn't end the session.
+fn log(args: fmt::Arguments) {
+    eprintln!("Terminal Log: {}", args);
+}
+
+/// Where replies to the output are written, the same place os-terminal writes its own.
+pub(crate) type ReplyWriter = Box<dyn Fn(&[u8]) + Send>;
+
+const BEGIN_SYNCHRONIZED_UPDATE: &[u8] = b"\x1b[?2026h";
+const END_SYNCHRONIZED_UPDATE: &[u8] = b"\x1b[?2026l";
+// an update which hasn't ended after this long, or this much output, is shown anyway, as
+// other terminals do, so a program which never ends one doesn't freeze the window.
+const SYNCHRONIZED_UPDATE_TIMEOUT: Duration = Duration::from_secs(1);
+const MAX_SYNCHRONIZED_UPDATE: usize = 4 << 20;
+
+/// Returns how much of the output can be shown without revealing part of a synchronized update
+/// (DEC mode 2026), which must appear all at once.
+fn complete_frame_len(output: &[u8]) -> usize {
+    let mut synchronized = false;
+    let mut complete = 0;
+    let mut index = 0;
+    while index < output.len() {
+        let rest = &output[index..];
+        if rest.starts_with(BEGIN_SYNCHRONIZED_UPDATE) {
+            synchronized = true;
+            index += BEGIN_SYNCHRONIZED_UPDATE.len();
+        } else if rest.starts_with(END_SYNCHRONIZED_UPDATE) {
+            synchronized = false;
+            index += END_SYNCHRONIZED_UPDATE.len();
+        } else if BEGIN_SYNCHRONIZED_UPDATE.starts_with(rest) {
+            // the rest of the output may be the start of an update which was cut off.
+            break;
+        } else {
+            index += 1;
+        }
+        if !synchronized {
+            complete = index;
+        }
+    }
+    complete
+}
+
+/// Passes the TUI's output to os-terminal one frame at a time. Output is held until the backend
+/// is flushed, which ratatui does once a frame is completely written, so the window never
+/// presents half a frame.
+struct TerminalWriter {
+    terminal: Arc<Mutex<Terminal<Display>>>,
+    pending_draw: Arc<AtomicBool>,
+    output: Vec<u8>,
+    /// When the synchronized update being held started to be held.
+    held_since: Option<Instant>,
+    recorder: Option<Arc<Recorder>>,
+    /// Takes out sixel images, which os-terminal would drop, to draw them itself.
+    sixels: Option<SixelFilter>,
+    /// Takes out clipboard sequences, which os-terminal would drop when they're long.
+    clipboard: Option<ClipboardFilter>,
+}
+
+impl TerminalWriter {
+    fn new(
+        terminal: Arc<Mutex<Terminal<Display>>>,
+        pending_draw: Arc<AtomicBool>,
+        recorder: Option<Arc<Recorder>>,
+        sixels: Option<SixelFilter>,
+        clipboard: Option<ClipboardFilter>,
+    ) -> Self {
+        Self {
+            terminal,
+            pending_draw,
+            output: Vec::new(),
+            held_since: None,
+            recorder,
+            sixels,
+            clipboard,
+        }
+    }
+
+    /// Returns how long until the synchronized update being held is shown anyway, none if
+    /// nothing is held.
+    fn held_timeout(&self) -> Option<Duration> {
+        let held_since = self.held_since?;
+        Some(SYNCHRONIZED_UPDATE_TIMEOUT.saturating_sub(held_since.elapsed()))
+    }
+
+    /// Returns how much of the output can be shown, which is all of it once an update has been
+    /// held for too long.
+    fn complete_len(&mut self) -> usize {
+        let complete = complete_frame_len(&self.output);
+        if complete == self.output.len() {
+            self.held_since = None;
+            return complete;
+        }
+        let expired = self.held_timeout() == Some(Duration::ZERO)
+            || self.output.len() - complete > MAX_SYNCHRONIZED_UPDATE;
+        if expired {
+            self.held_since = None;
+            return self.output.len();
+        }
+        // an update which starts after the output shown is held from now on.
+        if complete > 0 || self.held_since.is_none() {
+            self.held_since = Some(Instant::now());
+        }
+        complete
+    }
+}
+
+impl std::io::Write for TerminalWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let complete = self.complete_len();
+        if complete == 0 {
+            return Ok(());
+        }
+        // frames are recorded whole, the same way they are presented.
+        if let Some(recorder) = &self.recorder {
+            recorder.output(&self.output[..complete])?;
+        }
+        let mut output = Cow::Borrowed(&self.output[..complete]);
+        if let Some(sixels) = &mut self.sixels {
+            let mut filtered = Vec::new();
+            sixels.filter(&output, &mut filtered);
+            output = Cow::Owned(filtered);
+        }
+        if let Some(clipboard) = &mut self.clipboard {
+            let mut filtered = Vec::new();
+            clipboard.filter(&output, &mut filtered);
+            output = Cow::Owned(filtered);
+        }
+        if let Ok(mut term) = self.terminal.lock() {
+            term.process(&output);
+            self.pending_draw.store(true, Ordering::Relaxed);
+        }
+        self.output.drain(..complete);
+        Ok(())
+    }
+}
+
+/// The size of the grid, shared between the window, which lays it out, and everything which has
+/// to report it.
+#[derive(Clone, Copy)]
+pub(crate) struct ScreenSize {
+    pub(crate) cols: u16,
+    pub(crate) rows: u16,
+    /// The size of a cell, in pixels.
+    pub(crate) cell_width: u16,
+    pub(crate) cell_height: u16,
+}
+
+impl ScreenSize {
+    fn new(cols: usize, rows: usize, cell_size: (usize, usize)) -> Self {
+        Self {
+            cols: cols as u16,
+            rows: rows as u16,
+            cell_width: cell_size.0 as u16,
+            cell_height: cell_size.1 as u16,
+        }
+    }
+
+    /// The size of the grid, in pixels. Any partial cells at the edges of the window aren't
+    /// part of it.
+    pub(crate) fn pixels(&self) -> Size {
+        Size::new(
+            self.cols.saturating_mul(self.cell_width),
+            self.rows.saturating_mul(self.cell_height),
+        )
+    }
+}
+
+/// A crossterm backend whose output goes to os-terminal rather than a real tty. The cursor is
+/// tracked here, because asking the terminal for it would query the process's own stdin and
+/// stdout instead of the one in the window.
+pub struct VirtualBackend<W: io::Write> {
+    inner: CrosstermBackend<W>,
+    size: Arc<Mutex<ScreenSize>>,
+    cursor: Position,
+    cursor_visible: bool,
+    text: Option<Arc<Mutex<ScreenText>>>,
+    shown_cursor: Option<Arc<Mutex<Option<Position>>>>,
+    modes: Option<Arc<Mutex<ModeScanner>>>,
+}
+
+impl<W: io::Write> VirtualBackend<W> {
+    pub fn new(inner: CrosstermBackend<W>, size: Arc<Mutex<ScreenSize>>) -> Self {
+        Self {
+            inner,
+            size,
+            cursor: Position::ORIGIN,
+            cursor_visible: true,
+            text: None,
+            shown_cursor: None,
+            modes: None,
+        }
+    }
+
+    /// Keeps what is drawn in each cell, for the window to copy selected text from.
+    pub(crate) fn with_text(mut self, text: Arc<Mutex<ScreenText>>) -> Self {
+        self.text = Some(text);
+        self
+    }
+
+    /// Keeps the cell the cursor was last shown in, for the window to place input methods at.
+    pub(crate) fn with_shown_cursor(mut self, cursor: Arc<Mutex<Option<Position>>>) -> Self {
+        self.shown_cursor = Some(cursor);
+        self
+    }
+
+    /// Follows the modes the TUI sets in what it writes, for the window to know which key events
+    /// it wants.
+    pub(crate) fn with_modes(mut self, modes: Arc<Mutex<ModeScanner>>) -> Self {
+        self.modes = Some(modes);
+        self
+    }
+
+    /// Keeps the cursor on the grid, the same way a terminal would.
+    fn clamp_cursor(&mut self) {
+        let ScreenSize { cols, rows, .. } = *self.size.lock().unwrap();
+        self.cursor.x = self.cursor.x.min(cols.saturating_sub(1));
+        self.cursor.y = self.cursor.y.min(rows.saturating_sub(1));
+    }
+}
+
+impl<W: io::Write> io::Write for VirtualBackend<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.inner.write(buf)?;
+        if let Some(modes) = &self.modes {
+            modes.lock().unwrap().scan(&buf[..len]);
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Backend::flush(&mut self.inner)
+    }
+}
+
+impl<W: io::Write> Backend for VirtualBackend<W> {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        let mut text = self.text.as_ref().map(|text| text.lock().unwrap());
+        if let Some(text) = &mut text {
+            let ScreenSize { cols, rows, .. } = *self.size.lock().unwrap();
+            text.resize(Size::new(cols, rows));
+        }
+        // crossterm leaves the cursor just after the last cell it printed.
+        let mut last = None;
+        self.inner.draw(content.inspect(|(x, y, cell)| {
+            last = Some((*x, *y, *cell));
+            if let Some(text) = &mut text {
+                text.set(*x, *y, cell.symbol());
+            }
+        }))?;
+        drop(text);
+        if let Some((x, y, cell)) = last {
+            let width = Span::raw(cell.symbol()).width() as u16;
+            self.cursor = Position::new(x.saturating_add(width), y);
+            self.clamp_cursor();
+        }
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        self.inner.hide_cursor()?;
+        self.cursor_visible = false;
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        self.inner.show_cursor()?;
+        self.cursor_visible = true;
+        Ok(())
+    }
+
+    fn get_cursor_position(&mut self) -> io::Result<Position> {
+        Ok(self.cursor)
+    }
+
+    fn set_cursor_position<P: Into<ratatui::layout::Position>>(
+        &mut self,
+        position: P,
+    ) -> io::Result<()> {
+        let position = position.into();
+        self.inner.set_cursor_position(position)?;
+        self.cursor = position;
+        self.clamp_cursor();
+        Ok(())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        if let Some(text) = &self.text {
+            text.lock().unwrap().clear();
+        }
+        self.inner.clear()?;
+        // clearing the whole screen sends the terminal's cursor home.
+        self.cursor = Position::ORIGIN;
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<Size> {
+        let size = *self.size.lock().unwrap();
+        Ok(Size::new(size.cols, size.rows))
+    }
+
+    fn window_size(&mut self) -> io::Result<WindowSize> {
+        let size = *self.size.lock().unwrap();
+        Ok(WindowSize {
+            columns_rows: Size::new(size.cols, size.rows),
+            pixels: size.pixels(),
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // ratatui places the cursor after drawing, this is the end of the frame.
+        if let Some(cursor) = &self.shown_cursor
+            && self.cursor_visible
+        {
+            *cursor.lock().unwrap() = Some(self.cursor);
+        }
+        Backend::flush(&mut self.inner)?;
+        // the cells are with the terminal now, so the overlays can go over them.
+        commit_overlays();
+        Ok(())
+    }
+
+    fn append_lines(&mut self, n: u16) -> io::Result<()> {
+        // each line feed moves the cursor down, until the terminal has to scroll instead.
+        self.inner.append_lines(n)?;
+        self.cursor.y = self.cursor.y.saturating_add(n);
+        self.clamp_cursor();
+        Ok(())
+    }
+
+    fn get_cursor(&mut self) -> io::Result<(u16, u16)> {
+        let ratatui::prelude::Position { x, y } = self.get_cursor_position()?;
+        Ok((x, y))
+    }
+
+    fn set_cursor(&mut self, x: u16, y: u16) -> io::Result<()> {
+        self.set_cursor_position(ratatui::prelude::Position { x, y })
+    }
+}
+
+fn indexed_to_rgb(index: u8, theme: &Theme) -> Rgb {
+    match index {
+        0..16 => theme.ansi_colors[index as usize],
+        16..232 => {
+            // 6x6x6 color cube, using the same levels as os-terminal.
+            let index = index - 16;
+            let scale = |c: u8| if c == 0 { 0 } else { c * 40 + 55 };
+            (scale(index / 36), scale((index % 36) / 6), scale(index % 6))
+        }
+        _ => {
+            let level = (index - 232) * 10 + 8;
+            (level, level, level)
+        }
+    }
+}
+
+fn color_to_rgb(color: Color, default: Rgb, theme: &Theme) -> Rgb {
+    match color {
+        Color::Reset => default,
+        Color::Black => indexed_to_rgb(0, theme),
+        Color::Red => indexed_to_rgb(1, theme),
+        Color::Green => indexed_to_rgb(2, theme),
+        Color::Yellow => indexed_to_rgb(3, theme),
+        Color::Blue => indexed_to_rgb(4, theme),
+        Color::Magenta => indexed_to_rgb(5, theme),
+        Color::Cyan => indexed_to_rgb(6, theme),
+        Color::Gray => indexed_to_rgb(7, theme),
+        Color::DarkGray => indexed_to_rgb(8, theme),
+        Color::LightRed => indexed_to_rgb(9, theme),
+        Color::LightGreen => indexed_to_rgb(10, theme),
+        Color::LightYellow => indexed_to_rgb(11, theme),
+        Color::LightBlue => indexed_to_rgb(12, theme),
+        Color::LightMagenta => indexed_to_rgb(13, theme),
+        Color::LightCyan => indexed_to_rgb(14, theme),
+        Color::White => indexed_to_rgb(15, theme),
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(index) => indexed_to_rgb(index, theme),
+    }
+}
+
+/// Calls `draw` with the position and intensity of every pixel of a rasterized glyph, and
+/// returns how wide the glyph is, none if it has no pixels.
+fn draw_raster(raster: Rasterized, mut draw: impl FnMut(usize, usize, u8)) -> Option<usize> {
+    let lines: &mut dyn Iterator<Item = &[u8]> = match &raster {
+        Rasterized::Slice(raster) => &mut raster.iter().copied(),
+        Rasterized::Vec(raster) => &mut raster.iter().map(Vec::as_slice),
+        Rasterized::Owned(raster) => &mut raster.iter().map(Vec::as_slice),
+    };
+    let mut width = None;
+    for (y, line) in lines.enumerate() {
+        width.get_or_insert(line.len());
+        for (x, &intensity) in line.iter().enumerate() {
+            draw(x, y, intensity);
+        }
+    }
+    width
+}
+
+fn blend(foreground: Rgb, background: Rgb, intensity: u8) -> Rgb {
+    let intensity = intensity as u32;
+    let mix = |f: u8, b: u8| ((f as u32 * intensity + b as u32 * (255 - intensity)) / 255) as u8;
+    (
+        mix(foreground.0, background.0),
+        mix(foreground.1, background.1),
+        mix(foreground.2, background.2),
+    )
+}
+
+/// A grid of ratatui cells which is rasterized directly into a [`Display`] with the same font
+/// os-terminal uses. Cells are queued by `draw` and only rasterized on `flush`.
+struct PixelCanvas {
+    display: Display,
+    font: Box<dyn FontManager>,
+    size: Size,
+    cells: Vec<Cell>,
+    dirty: Vec<Position>,
+    cursor: Position,
+    cursor_visible: bool,
+    drawn_cursor: Option<Position>,
+    theme: Theme,
+}
+
+impl PixelCanvas {
+    fn new(display: Display, fonts: &Fonts, theme: &Theme) -> Self {
+        let mut canvas = Self {
+            display,
+            font: Box::new(FallbackFont::new(fonts)),
+            size: Size::default(),
+            cells: Vec::new(),
+            dirty: Vec::new(),
+            cursor: Position::ORIGIN,
+            cursor_visible: true,
+            drawn_cursor: None,
+            theme: theme.clone(),
+        };
+        canvas.update_size();
+        canvas
+    }
+
+    fn resize(&mut self, display: Display) {
+        self.display = display;
+        self.update_size();
+    }
+
+    /// Changes the font, which takes effect with the next resize.
+    fn set_font(&mut self, fonts: &Fonts) {
+        self.font = Box::new(FallbackFont::new(fonts));
+    }
+
+    /// Lays the grid out again on the display. The cells are kept if there are as many as
+    /// before, the TUI only draws them again when it's told the grid changed size.
+    fn update_size(&mut self) {
+        let (cell_width, cell_height) = self.font.size();
+        let size = Size::new(
+            (self.display.width / cell_width) as u16,
+            (self.display.height / cell_height) as u16,
+        );
+        if size == self.size {
+            self.redraw();
+        } else {
+            self.size = size;
+            self.clear();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.cells = vec![Cell::default(); self.size.width as usize * self.size.height as usize];
+        self.dirty.clear();
+        self.fill_background();
+    }
+
+    /// Switches to another theme, and draws every cell again in its colors.
+    fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+        self.redraw();
+    }
+
+    fn redraw(&mut self) {
+        self.fill_background();
+        self.dirty.clear();
+        for y in 0..self.size.height {
+            let mut x = 0;
+            while x < self.size.width {
+                self.dirty.push(Position::new(x, y));
+                // the cells a wide character covers are left alone, as the TUI's draws leave
+                // them, so the glyph isn't painted over.
+                let cell = &self.cells[y as usize * self.size.width as usize + x as usize];
+                x += (Span::raw(cell.symbol()).width() as u16).max(1);
+            }
+        }
+        self.flush();
+    }
+
+    fn fill_background(&mut self) {
+        self.drawn_cursor = None;
+        // this covers the partial cells at the edges as well.
+        for y in 0..self.display.height {
+            for x in 0..self.display.width {
+                self.display.draw_pixel(x, y, self.theme.background);
+            }
+        }
+    }
+
+    fn set_cell(&mut self, x: u16, y: u16, cell: &Cell) {
+        if x < self.size.width && y < self.size.height {
+            self.cells[y as usize * self.size.width as usize + x as usize] = cell.clone();
+            self.dirty.push(Position::new(x, y));
+        }
+    }
+
+    fn flush(&mut self) {
+        for position in std::mem::take(&mut self.dirty) {
+            self.paint(position, false);
+        }
+
+        if let Some(position) = self.drawn_cursor.take() {
+            self.paint(position, false);
+        }
+        let Position { x, y } = self.cursor;
+        if self.cursor_visible && x < self.size.width && y < self.size.height {
+            self.paint(self.cursor, true);
+            self.drawn_cursor = Some(self.cursor);
+        }
+    }
+
+    fn paint(&mut self, position: Position, cursor: bool) {
+        let (x, y) = (position.x as usize, position.y as usize);
+        let cell = &self.cells[y * self.size.width as usize + x];
+
+        let theme = &self.theme;
+        let mut foreground = color_to_rgb(cell.fg, theme.foreground, theme);
+        let mut background = color_to_rgb(cell.bg, theme.background, theme);
+        if cursor && let Some(color) = theme.cursor {
+            (foreground, background) = (theme.background, color);
+        } else if cell.modifier.contains(Modifier::REVERSED) != cursor {
+            std::mem::swap(&mut foreground, &mut background);
+        }
+        if cell.modifier.contains(Modifier::HIDDEN) {
+            foreground = background;
+        }
+
+        let info = ContentInfo {
+            content: cell.symbol().chars().next().unwrap_or(' '),
+            bold: cell.modifier.contains(Modifier::BOLD),
+            italic: cell.modifier.contains(Modifier::ITALIC),
+            wide: Span::raw(cell.symbol()).width() > 1,
+        };
+        let underline = cell.modifier.contains(Modifier::UNDERLINED);
+        let crossed_out = cell.modifier.contains(Modifier::CROSSED_OUT);
+
+        let (cell_width, cell_height) = self.font.size();
+        let (x_start, y_start) = (x * cell_width, y * cell_height);
+        let display = &mut self.display;
+        let glyph_width = draw_raster(self.font.rasterize(info), |x, y, intensity| {
+            let (x, y) = (x_start + x, y_start + y);
+            if x < display.width && y < display.height {
+                display.draw_pixel(x, y, blend(foreground, background, intensity));
+            }
+        })
+        .unwrap_or(cell_width);
+
+        let lines = [
+            underline.then_some(cell_height - 1),
+            crossed_out.then_some(cell_height / 2),
+        ];
+        let x_end = (x_start + glyph_width).min(display.width);
+        for y in lines.into_iter().flatten().map(|y| y_start + y) {
+            if y < display.height {
+                for x in x_start..x_end {
+                    display.draw_pixel(x, y, foreground);
+                }
+            }
+        }
+    }
+}
+
+/// A ratatui backend which rasterizes cells directly into the window's pixel buffer instead of
+/// producing ANSI escape sequences for os-terminal to interpret.
+pub struct PixelBackend {
+    canvas: Arc<Mutex<PixelCanvas>>,
+    pending_draw: Arc<AtomicBool>,
+    text: Arc<Mutex<ScreenText>>,
+    shown_cursor: Arc<Mutex<Option<Position>>>,
+    modes: Arc<Mutex<ModeScanner>>,
+}
+
+impl io::Write for PixelBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // there is no terminal to interpret escape sequences, so they are dropped once the modes
+        // have been followed.
+        self.modes.lock().unwrap().scan(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Backend for PixelBackend {
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        let mut canvas = self.canvas.lock().unwrap();
+        let mut text = self.text.lock().unwrap();
+        text.resize(canvas.size);
+        for (x, y, cell) in content {
+            canvas.set_cell(x, y, cell);
+            text.set(x, y, cell.symbol());
+        }
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        self.canvas.lock().unwrap().cursor_visible = false;
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        self.canvas.lock().unwrap().cursor_visible = true;
+        Ok(())
+    }
+
+    fn get_cursor_position(&mut self) -> io::Result<Position> {
+        Ok(self.canvas.lock().unwrap().cursor)
+    }
+
+    fn set_cursor_position<P: Into<Position>>(&mut self, position: P) -> io::Result<()> {
+        self.canvas.lock().unwrap().cursor = position.into();
+        Ok(())
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        let mut canvas = self.canvas.lock().unwrap();
+        canvas.clear();
+        // as in the terminal, the cursor goes home.
+        canvas.cursor = Position::ORIGIN;
+        drop(canvas);
+        self.text.lock().unwrap().clear();
+        Ok(())
+    }
+
+    fn size(&self) -> io::Result<Size> {
+        Ok(self.canvas.lock().unwrap().size)
+    }
+
+    fn window_size(&mut self) -> io::Result<WindowSize> {
+        let canvas = self.canvas.lock().unwrap();
+        let Size { width, height } = canvas.size;
+        let size = ScreenSize::new(width as usize, height as usize, canvas.font.size());
+        Ok(WindowSize {
+            columns_rows: canvas.size,
+            pixels: size.pixels(),
+        })
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut canvas = self.canvas.lock().unwrap();
+        canvas.flush();
+        if canvas.cursor_visible {
+            *self.shown_cursor.lock().unwrap() = Some(canvas.cursor);
+        }
+        drop(canvas);
+        commit_overlays();
+        self.pending_draw.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+struct GUIScreen {
+    input_rx: Receiver<ScreenEvent>,
+    size: Arc<Mutex<ScreenSize>>,
+    recorder: Option<Arc<Recorder>>,
+    overlays: Arc<Mutex<OverlayLayer>>,
+    pending_draw: Arc<AtomicBool>,
+    text: Arc<Mutex<ScreenText>>,
+    shown_cursor: Arc<Mutex<Option<Position>>>,
+    modes: Arc<Mutex<ModeScanner>>,
+}
+
+
+impl<W: Write> Screen<W> for GUIScreen {
+
+    type Backend = VirtualBackend<W>;
+
+    fn poll_and_read(&self, timeout: Duration) -> Result<Option<ScreenEvent>,Box<dyn Error>> {
+        let event = poll_input(&self.input_rx, timeout)?;
+        if let Some(recorder) = &self.recorder
+            && let Some(ScreenEvent::Input(event)) = &event
+        {
+            recorder.input(event)?;
+        }
+        Ok(event)
+    }
+
+    fn enable_raw_mode(&self) -> Result<(),Box<dyn Error>> {
+        // nothing needs to be done here...
+        Ok(())
+    }
+
+    fn disable_raw_mode(&self) -> Result<(),Box<dyn Error>> {
+        // nothing needs to be done here...
+        Ok(())
+    }
+
+    fn create_backend(&self, stdout: W) -> Self::Backend {
+        capture_overlays(self.size.clone(), self.overlays.clone(), self.pending_draw.clone());
+        let inner = CrosstermBackend::new(stdout);
+        VirtualBackend::new(inner, self.size.clone())
+            .with_text(self.text.clone())
+            .with_shown_cursor(self.shown_cursor.clone())
+            .with_modes(self.modes.clone())
+    }
+
+    fn resize(&self, cols: u16, rows: u16) {
+        let mut size = self.size.lock().unwrap();
+        size.cols = cols;
+        size.rows = rows;
+    }
+
+
+}
+
+struct PixelScreen {
+    input_rx: Receiver<ScreenEvent>,
+    canvas: Arc<Mutex<PixelCanvas>>,
+    size: Arc<Mutex<ScreenSize>>,
+    overlays: Arc<Mutex<OverlayLayer>>,
+    pending_draw: Arc<AtomicBool>,
+    text: Arc<Mutex<ScreenText>>,
+    shown_cursor: Arc<Mutex<Option<Position>>>,
+    modes: Arc<Mutex<ModeScanner>>,
+}
+
+impl<W: Write> Screen<W> for PixelScreen {
+
+    type Backend = PixelBackend;
+
+    fn poll_and_read(&self, timeout: Duration) -> Result<Option<ScreenEvent>,Box<dyn Error>> {
+        poll_input(&self.input_rx, timeout)
+    }
+
+    fn enable_raw_mode(&self) -> Result<(),Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn disable_raw_mode(&self) -> Result<(),Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn create_backend(&self, _stdout: W) -> Self::Backend {
+        capture_overlays(self.size.clone(), self.overlays.clone(), self.pending_draw.clone());
+        PixelBackend {
+            canvas: self.canvas.clone(),
+            pending_draw: self.pending_draw.clone(),
+            text: self.text.clone(),
+            shown_cursor: self.shown_cursor.clone(),
+            modes: self.modes.clone(),
+        }
+    }
+
+    fn resize(&self, _cols: u16, _rows: u16) {
+        // the backend reads its size from the canvas, which the window has already resized.
+    }
+
+}
+
+fn poll_input(
+    input_rx: &Receiver<ScreenEvent>,
+    timeout: Duration,
+) -> Result<Option<ScreenEvent>,Box<dyn Error>> {
+    match input_rx.recv_timeout(timeout) {
+        Ok(event) => Ok(Some(event)),
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+            Ok(None)
+        },
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+            Err("the window closed before the application quit".into())
+        }
+    }
+}
+
+fn run_tui_thread<A, W, S>(
+    app: A,
+    options: Options,
+    writer: W,
+    screen: S,
+    event_loop_proxy: EventLoopProxy<()>,
+) -> JoinHandle<Result<(), String>>
+where
+    A: TuiApp + Send + 'static,
+    W: Write + Send + 'static,
+    S: Screen<W> + Send + 'static,
+{
+    std::thread::spawn(move || {
+        // errors aren't Send, so only the message makes it back to the main thread.
+        let result = crossterm::run(app, &options, writer, screen).map_err(|e| e.to_string());
+        // send event to signal that the thread is done, the window may already be gone.
+        let _ = event_loop_proxy.send_event(());
+        result
+    })
+}
+
+/// Waits for the TUI thread to stop once the window has closed, and returns its result. The
+/// thread can't be interrupted, so it is left running if it doesn't stop in time.
+fn join_tui_thread(handle: JoinHandle<Result<(), String>>) -> Result<(), Box<dyn Error>> {
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    while !handle.is_finished() {
+        if Instant::now() >= deadline {
+            return Err("the application did not stop after the window closed".into());
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    match handle.join() {
+        Ok(result) => Ok(result?),
+        Err(_) => Err("the application panicked".into()),
+    }
+}
+
+/// Starts recording the session if the options ask for it.
+fn create_recorder(
+    options: &Options,
+    cols: usize,
+    rows: usize,
+) -> Result<Option<Arc<Recorder>>, Box<dyn Error>> {
+    let Some(path) = &options.record else {
+        return Ok(None);
+    };
+    let recorder = Recorder::create(path, cols as u16, rows as u16, &options.title)
+        .map_err(|e| format!("could not create recording {}: {}", path.display(), e))?;
+    Ok(Some(Arc::new(recorder)))
+}
+
+/// Decodes input written by the terminal and sends every resulting event to the TUI. Malformed
+/// input is reported and skipped rather than ending the session.
+fn send_term_input(decoder: &mut InputDecoder, data: &[u8], input_tx: &Sender<ScreenEvent>) {
+    send_decoded(decoder.decode(data), input_tx);
+}
+
+/// Sends the start of an escape sequence which was never finished, once the decoder has given
+/// up waiting for the rest of it.
+fn send_expired_input(decoder: &mut InputDecoder, input_tx: &Sender<ScreenEvent>) {
+    send_decoded(decoder.expire(Instant::now()), input_tx);
+}
+
+fn send_decoded(
+    events: impl IntoIterator<Item = Result<Event, MalformedInput>>,
+    input_tx: &Sender<ScreenEvent>,
+) {
+    for event in events {
+        match event {
+            // the TUI may have stopped already, in which case nobody is left to read the input.
+            Ok(event) => {
+                let _ = input_tx.send(event.into());
+            }
+            Err(e) => log(format_args!("skipped {}", e)),
+        }
+    }
+}
+
+/// Copies the lines the terminal has in view to the text selections are made from.
+fn copy_terminal_text(terminal: &Terminal<Display>, text: &mut ScreenText) {
+    let (cols, rows) = (terminal.columns(), terminal.rows());
+    text.resize(Size::new(cols as u16, rows as u16));
+    let mut symbol = [0; 4];
+    for row in 0..rows {
+        for column in 0..cols {
+            let content = terminal.cell_content(row, column);
+            let symbol = content.map_or("", |c| c.encode_utf8(&mut symbol));
+            text.set(column as u16, row as u16, symbol);
+        }
+    }
+}
+
+/// Creates a terminal which draws into the given display. Settings which are not stored
+/// globally by os-terminal must be applied here.
+fn create_terminal(display: Display, fonts: &Fonts) -> Terminal<Display> {
+    let mut terminal = Terminal::new(display);
+    terminal.set_scroll_speed(SCROLL_SPEED);
+    terminal.set_font_manager(Box::new(FallbackFont::new(fonts)));
+    terminal.set_history_size(HISTORY_SIZE);
+    terminal
+}
+
+/// Sends anything the terminal writes back, such as replies to status queries, to the TUI as
+/// input. Returns the decoder, which has to be [expired](InputDecoder::expire) now and then.
+fn forward_pty_input(
+    terminal: &mut Terminal<Display>,
+    input_tx: Sender<ScreenEvent>,
+) -> Arc<Mutex<InputDecoder>> {
+    let decoder = Arc::new(Mutex::new(InputDecoder::new()));
+    terminal.set_pty_writer({
+        let decoder = decoder.clone();
+        Box::new(move |data| {
+            send_term_input(&mut decoder.lock().unwrap(), data.as_bytes(), &input_tx)
+        })
+    });
+    decoder
+}
+
+/// Runs the application in a window, on a separate thread from the window's event loop. This
+/// must be called from the main thread.
+///
+/// Control with plus, minus or zero zooms the text in, out or back to the configured size. The
+/// application is told about the new size like any other resize, and the zoom level is kept for
+/// the next session.
+/// Control, shift and T switches to the next of the [themes](Options::themes).
+/// Control, shift and V pastes the clipboard, and the middle mouse button the primary
+/// selection. Either way the application gets the whole text as one [`Event::Paste`].
+///
+/// Dragging with shift held selects text, even though the application has the mouse. Clicking
+/// twice selects words, three times whole lines. The selection is copied to the clipboard when
+/// the button is released, and again with control, shift and C. The application doesn't see
+/// any of these clicks.
+///
+/// Text being composed with an input method is shown underlined at the application's cursor,
+/// or where the cursor was last shown if it is hidden, until it is committed.
+pub fn run_windowed<A>(app: A, options: Options) -> Result<(), Box<dyn Error>>
+where
+    A: TuiApp + Send + 'static,
+{
+    // options which can't be used together are rejected before anything is set up.
+    if options.record.is_some() && options.render_mode == RenderMode::Pixel {
+        return Err("recording is only possible in the terminal render mode".into());
+    }
+
+    let display = Display::default();
+    let fonts = Fonts::load(&options.font)?.with_zoom(restore_zoom());
+    let themes = Themes::new(&options)?;
+
+    let (input_tx, input_rx) = channel::<ScreenEvent>();
+
+    let mut terminal = create_terminal(display.clone(), &fonts);
+    terminal.set_custom_color_scheme(&themes.current().palette());
+    terminal.set_auto_flush(false);
+    terminal.set_logger(log);
+    let clipboard = Clipboard::new();
+    terminal.set_clipboard(Box::new(clipboard.clone()));
+
+    let pty_decoder = forward_pty_input(&mut terminal, input_tx.clone());
+
+    let recorder = create_recorder(&options, terminal.columns(), terminal.rows())?;
+    let size = ScreenSize::new(terminal.columns(), terminal.rows(), fonts.cell_size());
+    let size = Arc::new(Mutex::new(size));
+
+    let terminal = Arc::new(Mutex::new(terminal));
+    let pending_draw = Arc::new(AtomicBool::new(false));
+    let overlays = Arc::new(Mutex::new(OverlayLayer::default()));
+    let text = Arc::new(Mutex::new(ScreenText::default()));
+    let shown_cursor = Arc::new(Mutex::new(None));
+    let modes = Arc::new(Mutex::new(ModeScanner::new()));
+
+    let event_loop = EventLoop::new()?;
+    let event_loop_proxy = event_loop.create_proxy();
+    let title = options.title.clone();
+
+    // In pixel mode the TUI's output never reaches the terminal, the canvas is presented instead.
+    let canvas = match options.render_mode {
+        RenderMode::Terminal => None,
+        RenderMode::Pixel => Some(Arc::new(Mutex::new(PixelCanvas::new(Display::default(), &fonts, themes.current())))),
+    };
+
+    let tui_thread = match &canvas {
+        None => {
+            let sixels = SixelFilter::new(size.clone(), overlays.clone());
+            let writer = TerminalWriter::new(
+                terminal.clone(),
+                pending_draw.clone(),
+                recorder.clone(),
+                Some(sixels),
+                Some(ClipboardFilter::new(clipboard.clone(), None)),
+            );
+            let screen = GUIScreen {
+                input_rx,
+                size: size.clone(),
+                recorder,
+                overlays: overlays.clone(),
+                pending_draw: pending_draw.clone(),
+                text: text.clone(),
+                shown_cursor: shown_cursor.clone(),
+                modes: modes.clone(),
+            };
+            run_tui_thread(app, options, writer, screen, event_loop_proxy)
+        }
+        Some(canvas) => {
+            let screen = PixelScreen {
+                input_rx,
+                canvas: canvas.clone(),
+                size: size.clone(),
+                overlays: overlays.clone(),
+                pending_draw: pending_draw.clone(),
+                text: text.clone(),
+                shown_cursor: shown_cursor.clone(),
+                modes: modes.clone(),
+            };
+            run_tui_thread(app, options, io::sink(), screen, event_loop_proxy)
+        }
+    };
+
+    let mut handler = App::new(
+        title,
+        display,
+        terminal.clone(),
+        canvas,
+        pending_draw.clone(),
+        input_tx,
+        size,
+        fonts,
+        themes,
+        clipboard,
+    );
+    handler.overlays = overlays;
+    handler.pty_decoder = Some(pty_decoder);
+    handler.text = Some(text);
+    handler.shown_cursor = shown_cursor;
+    handler.tui_modes = Some(modes);
+
+    let result = event_loop.run_app(&mut handler);
+    drop(handler);
+
+    join_tui_thread(tui_thread)?;
+    Ok(result?)
+}
+
+/// The pixels the terminal or canvas draws into, shared with the window. Rows are marked as
+/// damaged when a pixel in them changes, so that the window only copies and presents what
+/// changed since the last frame.
+#[derive(Clone)]
+struct Display {
+    width: usize,
+    height: usize,
+    buffer: Arc<Vec<AtomicU32>>,
+    damaged_rows: Arc<Vec<AtomicBool>>,
+}
+
+impl Display {
+    fn new(width: usize, height: usize) -> Self {
+        let buffer = (0..width * height)
+            .map(|_| AtomicU32::new(0))
+            .collect::<Vec<_>>();
+        // nothing has been presented yet, so every row starts out damaged.
+        let damaged_rows = (0..height).map(|_| AtomicBool::new(true)).collect::<Vec<_>>();
+
+        Self {
+            width,
+            height,
+            buffer: Arc::new(buffer),
+            damaged_rows: Arc::new(damaged_rows),
+        }
+    }
+
+    /// Returns the rows damaged since the last call, merged into runs of adjacent rows, and marks
+    /// them as clean.
+    fn take_damage(&self) -> Vec<Range<usize>> {
+        let mut damage: Vec<Range<usize>> = Vec::new();
+        for (row, damaged) in self.damaged_rows.iter().enumerate() {
+            if !damaged.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+            match damage.last_mut() {
+                Some(rows) if rows.end == row => rows.end = row + 1,
+                _ => damage.push(row..row + 1),
+            }
+        }
+        damage
+    }
+
+    /// Copies the given rows into a frame of the same size.
+    fn copy_rows(&self, frame: &mut [u32], rows: &Range<usize>) {
+        let pixels = rows.start * self.width..rows.end * self.width;
+        let source = &self.buffer[pixels.clone()];
+        for (target, value) in frame[pixels].iter_mut().zip(source) {
+            *target = value.load(Ordering::Relaxed);
+        }
+    }
+}
+
+/// Presents output the way the window does, into a frame in memory, so that what a frame costs
+/// can be measured without a window. Only meant for the benchmarks.
+#[cfg(feature = "bench")]
+#[doc(hidden)]
+pub struct FrameBench {
+    terminal: Terminal<Display>,
+    display: Display,
+    frame: Vec<u32>,
+}
+
+#[cfg(feature = "bench")]
+impl FrameBench {
+    pub fn new(width: usize, height: usize) -> Result<Self, Box<dyn Error>> {
+        let fonts = Fonts::load(&Options::default().font)?;
+        let display = Display::new(width, height);
+        let mut terminal = create_terminal(display.clone(), &fonts);
+        terminal.set_auto_flush(false);
+        Ok(Self {
+            terminal,
+            frame: vec![0; width * height],
+            display,
+        })
+    }
+
+    /// Has the terminal process the output, and copies the rows it damaged into the frame.
+    /// Returns how many rows were copied.
+    pub fn present(&mut self, output: &[u8]) -> usize {
+        self.terminal.process(output);
+        self.terminal.flush();
+        let damage = self.display.take_damage();
+        for rows in &damage {
+            self.display.copy_rows(&mut self.frame, rows);
+        }
+        damage.iter().map(Range::len).sum()
+    }
+
+    /// Like [`present`](Self::present), but copies every row, as if nothing tracked damage.
+    pub fn present_all(&mut self, output: &[u8]) -> usize {
+        self.terminal.process(output);
+        self.terminal.flush();
+        self.display.take_damage();
+        let rows = 0..self.display.height;
+        self.display.copy_rows(&mut self.frame, &rows);
+        rows.len()
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new(DISPLAY_SIZE.0, DISPLAY_SIZE.1)
+    }
+}
+
+impl DrawTarget for Display {
+    fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    #[inline(always)]
+    fn draw_pixel(&mut self, x: usize, y: usize, color: Rgb) {
+        let color = (color.0 as u32) << 16 | (color.1 as u32) << 8 | color.2 as u32;
+        if self.buffer[y * self.width + x].swap(color, Ordering::Relaxed) != color {
+            self.damaged_rows[y].store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+struct App {
+    title: String,
+    display: Display,
+    terminal: Arc<Mutex<Terminal<Display>>>,
+    canvas: Option<Arc<Mutex<PixelCanvas>>>,
+    window: Option<Rc<Window>>,
+    surface: Option<Surface<Rc<Window>, Rc<Window>>>,
+    pending_draw: Arc<AtomicBool>,
+    input_tx: Sender<ScreenEvent>,
+    size: Arc<Mutex<ScreenSize>>,
+    ime_decoder: InputDecoder,
+    /// Decodes what the terminal writes back, on screens which pass it on to the TUI.
+    pty_decoder: Option<Arc<Mutex<InputDecoder>>>,
+    mouse: MouseTracker,
+    modifiers: ModifiersState,
+    fonts: Fonts,
+    themes: Themes,
+    clipboard: Clipboard,
+    cell_size: (usize, usize),
+    grid_size: (u16, u16),
+    /// The size the window is created with, in pixels.
+    initial_size: (usize, usize),
+    /// A grid size the window is asked to take, by a recording which was resized.
+    grid_request: Arc<Mutex<Option<(u16, u16)>>>,
+    /// Pixels the TUI draws over the display.
+    overlays: Arc<Mutex<OverlayLayer>>,
+    /// What the TUI drew, or the terminal showed, for screens where text can be selected.
+    text: Option<Arc<Mutex<ScreenText>>>,
+    /// The text and cursor are read from the terminal, on screens where nothing draws through
+    /// a backend.
+    follow_terminal: bool,
+    /// The modes the program has set, on screens hosting one through a pty.
+    modes: Option<Arc<Mutex<ModeScanner>>>,
+    /// The modes the TUI has set, on screens hosting one, which decide whether it is told about
+    /// key repeats and releases.
+    tui_modes: Option<Arc<Mutex<ModeScanner>>>,
+    selector: Selector,
+    /// The cell the cursor was last shown in, none until it has been or while it's out of view.
+    shown_cursor: Arc<Mutex<Option<Position>>>,
+    /// The cell the input method was last placed at.
+    ime_cursor: Option<Position>,
+    /// The text being composed with the input method.
+    preedit: String,
+}
+
+impl App {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        title: String,
+        display: Display,
+        terminal: Arc<Mutex<Terminal<Display>>>,
+        canvas: Option<Arc<Mutex<PixelCanvas>>>,
+        pending_draw: Arc<AtomicBool>,
+        input_tx: Sender<ScreenEvent>,
+        size: Arc<Mutex<ScreenSize>>,
+        fonts: Fonts,
+        themes: Themes,
+        clipboard: Clipboard,
+    ) -> Self {
+        Self {
+            title,
+            display,
+            terminal,
+            canvas,
+            window: None,
+            surface: None,
+            pending_draw,
+            input_tx,
+            size,
+            ime_decoder: InputDecoder::new(),
+            pty_decoder: None,
+            mouse: MouseTracker::new(),
+            modifiers: ModifiersState::empty(),
+            cell_size: fonts.cell_size(),
+            fonts,
+            themes,
+            clipboard,
+            grid_size: (0, 0),
+            initial_size: DISPLAY_SIZE,
+            grid_request: Arc::default(),
+            overlays: Arc::default(),
+            text: None,
+            follow_terminal: false,
+            modes: None,
+            tui_modes: None,
+            selector: Selector::default(),
+            shown_cursor: Arc::default(),
+            ime_cursor: None,
+            preedit: String::new(),
+        }
+    }
+
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        // a minimized window reports a zero size, there's nothing to draw in that case.
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return;
+        };
+        if let Some(surface) = self.surface.as_mut() {
+            surface.resize(width, height).unwrap();
+        }
+
+        // The display covers the whole window, not just the whole cells. Both the terminal and
+        // the canvas draw all of it, so any partial cells at the right and bottom edges are
+        // padded rather than showing stale pixels.
+        let display = Display::new(width.get() as usize, height.get() as usize);
+        self.display = display.clone();
+
+        let (cols, rows) = match &self.canvas {
+            Some(canvas) => {
+                let mut canvas = canvas.lock().unwrap();
+                canvas.resize(display);
+                (canvas.size.width as usize, canvas.size.height as usize)
+            }
+            None => {
+                // the terminal keeps its screens, history and modes, laid out in the new grid.
+                let mut terminal = self.terminal.lock().unwrap();
+                terminal.set_display(display);
+                (terminal.columns(), terminal.rows())
+            }
+        };
+        self.pending_draw.store(true, Ordering::Relaxed);
+        self.clear_selection();
+        // the cells may have changed size, which moves the cursor's cell.
+        self.ime_cursor = None;
+        *self.size.lock().unwrap() = ScreenSize::new(cols, rows, self.cell_size);
+
+        let grid_size = (cols as u16, rows as u16);
+        if grid_size != self.grid_size {
+            self.grid_size = grid_size;
+            self.send(Event::Resize(cols as u16, rows as u16));
+        }
+    }
+
+    /// Redraws everything with the fonts zoomed to the given level, and lets the TUI know how
+    /// many cells now fit in the window.
+    fn zoom(&mut self, zoom: i32) {
+        let fonts = self.fonts.with_zoom(zoom);
+        if fonts.zoom() == self.fonts.zoom() {
+            return;
+        }
+        if let Err(e) = save_zoom(fonts.zoom()) {
+            log(format_args!("could not save the zoom level: {}", e));
+        }
+        self.set_fonts(fonts);
+    }
+
+    /// Switches to other fonts, and lays out the grid again in cells of their size.
+    fn set_fonts(&mut self, fonts: Fonts) {
+        self.fonts = fonts;
+        self.cell_size = self.fonts.cell_size();
+        match &self.canvas {
+            Some(canvas) => canvas.lock().unwrap().set_font(&self.fonts),
+            None => {
+                let font = Box::new(FallbackFont::new(&self.fonts));
+                self.terminal.lock().unwrap().set_font_manager(font);
+            }
+        }
+
+        if let Some(window) = self.window.clone() {
+            let (cell_width, cell_height) = self.cell_size;
+            window.set_resize_increments(Some(PhysicalSize::new(
+                cell_width as f64,
+                cell_height as f64,
+            )));
+            self.resize(window.inner_size());
+        }
+    }
+
+    fn run_shortcut(&mut self, shortcut: Shortcut) {
+        match shortcut {
+            Shortcut::ZoomIn => self.zoom(self.fonts.zoom() + 1),
+            Shortcut::ZoomOut => self.zoom(self.fonts.zoom() - 1),
+            Shortcut::ResetZoom => self.zoom(0),
+            Shortcut::NextTheme => {
+                let theme = self.themes.next();
+                match &self.canvas {
+                    Some(canvas) => canvas.lock().unwrap().set_theme(theme),
+                    None => {
+                        let palette = theme.palette();
+                        self.terminal.lock().unwrap().set_custom_color_scheme(&palette);
+                    }
+                }
+                self.show_preedit();
+                self.pending_draw.store(true, Ordering::Relaxed);
+            }
+            Shortcut::Copy => self.copy_selection(),
+            Shortcut::Paste => self.paste(PasteSource::Clipboard),
+        }
+    }
+
+    fn start_selection(&mut self) {
+        if self.follow_terminal
+            && let Some(text) = &self.text
+        {
+            copy_terminal_text(&self.terminal.lock().unwrap(), &mut text.lock().unwrap());
+        }
+        if let Some(position) = self.mouse.cell() {
+            self.selector.press(position);
+            self.show_selection();
+        }
+    }
+
+    fn clear_selection(&mut self) {
+        self.selector.clear();
+        self.show_selection();
+    }
+
+    /// Highlights the selected cells, or removes the highlight once nothing is selected.
+    fn show_selection(&self) {
+        let Some(text) = &self.text else {
+            return;
+        };
+        let areas = self.selector.areas(&text.lock().unwrap());
+        if self.overlays.lock().unwrap().set_selection(&areas, self.cell_size) {
+            self.pending_draw.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Places the input method's candidate window, and the text being composed, at the cell
+    /// the cursor was last shown in. Neither is placed while that isn't known.
+    fn follow_cursor(&mut self) {
+        if self.follow_terminal {
+            let terminal = self.terminal.lock().unwrap();
+            let mut shown_cursor = self.shown_cursor.lock().unwrap();
+            match terminal.cursor_position() {
+                Some((row, column)) if terminal.cursor_visible() => {
+                    *shown_cursor = Some(Position::new(column as u16, row as u16));
+                }
+                Some(_) => {}
+                None => *shown_cursor = None,
+            }
+        }
+        let cursor = *self.shown_cursor.lock().unwrap();
+        if self.ime_cursor == cursor {
+            return;
+        }
+        self.ime_cursor = cursor;
+        if let Some(window) = &self.window
+            && let Some(cursor) = cursor
+        {
+            let (cell_width, cell_height) = self.cell_size;
+            let (x, y) = (cursor.x as usize * cell_width, cursor.y as usize * cell_height);
+            window.set_ime_cursor_area(
+                PhysicalPosition::new(x as f64, y as f64),
+                PhysicalSize::new(cell_width as f64, cell_height as f64),
+            );
+        }
+        self.show_preedit();
+    }
+
+    fn show_preedit(&self) {
+        let cursor = *self.shown_cursor.lock().unwrap();
+        let (cell_width, cell_height) = self.cell_size;
+        let (x, y, pixels) = match cursor {
+            Some(cursor) if !self.preedit.is_empty() => (
+                cursor.x as usize * cell_width,
+                cursor.y as usize * cell_height,
+                Some(draw_preedit(&self.preedit, &self.fonts, self.themes.current())),
+            ),
+            _ => (0, 0, None),
+        };
+        if self.overlays.lock().unwrap().set_preedit(x, y, pixels) {
+            self.pending_draw.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn copy_selection(&self) {
+        let Some(text) = &self.text else {
+            return;
+        };
+        let selected = self.selector.text(&text.lock().unwrap());
+        if let Some(selected) = selected.filter(|selected| !selected.is_empty()) {
+            self.clipboard.copy(selected);
+        }
+    }
+
+    fn paste(&self, source: PasteSource) {
+        if let Some(text) = self.clipboard.paste(source).filter(|text| !text.is_empty()) {
+            self.send(Event::Paste(text));
+        }
+    }
+
+    /// Whether the application is told about the mouse. A TUI always is, a program only once it
+    /// has asked to be, and a recording never is.
+    fn app_has_mouse(&self) -> bool {
+        match &self.modes {
+            Some(scanner) => scanner.lock().unwrap().modes().mouse != MouseTracking::Off,
+            None => !self.follow_terminal,
+        }
+    }
+
+    /// Returns the key as the TUI should see it. Like a terminal, repeats are only told apart
+    /// from presses and releases are only reported once the TUI has pushed the keyboard
+    /// enhancement flag which asks for them.
+    fn key_for_tui(&self, key: KeyEvent) -> Option<KeyEvent> {
+        let reported = self.tui_modes.as_ref().is_none_or(|scanner| {
+            scanner.lock().unwrap().modes().report_event_types
+        });
+        if reported { Some(key) } else { without_event_type(key) }
+    }
+
+    /// Passes an event on to the TUI. Once the TUI thread has stopped there is nobody left to
+    /// receive it, and the window is about to close anyway.
+    fn send(&self, event: impl Into<ScreenEvent>) {
+        let _ = self.input_tx.send(event.into());
+    }
+}
+
+impl ApplicationHandler for App {
+    fn new_events(&mut self, _: &ActiveEventLoop, cause: StartCause) {
+        send_expired_input(&mut self.ime_decoder, &self.input_tx);
+        if let Some(decoder) = &self.pty_decoder {
+            send_expired_input(&mut decoder.lock().unwrap(), &self.input_tx);
+        }
+        if let Some(window) = &self.window
+            && let Some((cols, rows)) = self.grid_request.lock().unwrap().take()
+            && (cols, rows) != self.grid_size
+        {
+            let (cell_width, cell_height) = self.cell_size;
+            let (width, height) = (cols as usize * cell_width, rows as usize * cell_height);
+            let _ = window.request_inner_size(PhysicalSize::new(width as f64, height as f64));
+        }
+        if !matches!(cause, StartCause::ResumeTimeReached { .. })
+            || !self.pending_draw.swap(false, Ordering::Relaxed)
+        {
+            return;
+        }
+        self.follow_cursor();
+        if let Some(surface) = self.surface.as_mut() {
+            // In pixel mode the canvas is rasterized by the TUI thread, holding the lock keeps a
+            // partially rasterized frame from being copied.
+            let _canvas = match &self.canvas {
+                Some(canvas) => Some(canvas.lock().unwrap()),
+                None => {
+                    self.terminal.lock().unwrap().flush();
+                    None
+                }
+            };
+
+            let mut overlays = self.overlays.lock().unwrap();
+            let height = self.display.height;
+            let mut damage = self.display.take_damage();
+            // overlays drawn before the window was resized may reach past the display.
+            damage.extend(
+                overlays
+                    .take_damage()
+                    .into_iter()
+                    .map(|rows| rows.start.min(height)..rows.end.min(height))
+                    .filter(|rows| !rows.is_empty()),
+            );
+            if damage.is_empty() {
+                return;
+            }
+
+            let mut buffer = surface.buffer_mut().unwrap();
+            // only a buffer which still holds the last presented frame can be patched, any other
+            // has to be filled in completely.
+            let damage = if buffer.age() == 1 {
+                damage
+            } else {
+                std::iter::once(0..self.display.height).collect()
+            };
+
+            let width = self.display.width;
+            for rows in &damage {
+                self.display.copy_rows(&mut buffer, rows);
+                overlays.composite(&mut buffer, width, rows);
+            }
+
+            let rects = damage
+                .iter()
+                .filter_map(|rows| {
+                    Some(Rect {
+                        x: 0,
+                        y: rows.start as u32,
+                        width: NonZeroU32::new(width as u32)?,
+                        height: NonZeroU32::new(rows.len() as u32)?,
+                    })
+                })
+                .collect::<Vec<_>>();
+            buffer.present_with_damage(&rects).unwrap();
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        let refresh_rate = event_loop
+            .primary_monitor()
+            .and_then(|m| m.refresh_rate_millihertz())
+            .unwrap_or(60000);
+
+        let frame_duration = 1000.0 / (refresh_rate as f32 / 1000.0);
+        let duration = Duration::from_millis(frame_duration as u64);
+        event_loop.set_control_flow(ControlFlow::WaitUntil(Instant::now() + duration));
+    }
+
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let (width, height) = self.initial_size;
+        let (cell_width, cell_height) = self.cell_size;
+        // resize increments snap the window to whole cells where the window manager supports it.
+        let attributes = WindowAttributes::default()
+            .with_title(self.title.as_str())
+            .with_resizable(true)
+            .with_inner_size(PhysicalSize::new(width as f64, height as f64))
+            .with_resize_increments(PhysicalSize::new(cell_width as f64, cell_height as f64));
+
+        let window = Rc::new(event_loop.create_window(attributes).unwrap());
+        window.set_ime_allowed(true);
+        window.set_ime_purpose(ImePurpose::Terminal);
+
+        let context = Context::new(window.clone()).unwrap();
+        let surface = Surface::new(&context, window.clone()).unwrap();
+        let fonts = self.fonts.with_scale_factor(window.scale_factor());
+
+        self.window = Some(window);
+        self.surface = Some(surface);
+
+        self.set_fonts(fonts);
+    }
+
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, _: ()) {
+        // if I receive this then the terminal loop is done...
+        event_loop.exit();
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
+        match event {
+            // the application decides whether to close, the window closes once the TUI thread
+            // stops. If it has already stopped there is nothing to wait for.
+            WindowEvent::CloseRequested
+                if self.input_tx.send(ScreenEvent::CloseRequested).is_err() =>
+            {
+                event_loop.exit();
+            }
+            WindowEvent::Resized(size) => {
+                self.resize(size);
+            }
+            // text keeps its size on the monitor the window moved to.
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.set_fonts(self.fonts.with_scale_factor(scale_factor));
+            }
+            WindowEvent::Ime(Ime::Preedit(text, _)) => {
+                self.preedit = text;
+                self.show_preedit();
+            }
+            WindowEvent::Ime(Ime::Commit(text)) => {
+                send_term_input(&mut self.ime_decoder, text.as_bytes(), &self.input_tx);
+            }
+            WindowEvent::Ime(Ime::Disabled) => {
+                self.preedit.clear();
+                self.show_preedit();
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let event =
+                    self.mouse
+                        .moved(position, self.cell_size, self.grid_size, self.modifiers);
+                if let Some(event) = event {
+                    match self.mouse.cell() {
+                        Some(position) if self.selector.dragging() => {
+                            self.selector.drag(position);
+                            self.show_selection();
+                        }
+                        _ => self.send(Event::Mouse(event)),
+                    }
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.mouse.left();
+            }
+            // the middle button pastes, so the application never sees it.
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Middle,
+                ..
+            } => {
+                self.paste(PasteSource::Primary);
+            }
+            WindowEvent::MouseInput {
+                button: MouseButton::Middle,
+                ..
+            } => {}
+            // the left button selects text when the application isn't told about the mouse, and
+            // with shift when it is, as in other terminals.
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if self.text.is_some() && (self.modifiers.shift_key() || !self.app_has_mouse()) => {
+                self.start_selection();
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } if self.selector.dragging() => {
+                self.selector.release();
+                self.copy_selection();
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                // any other click lets go of the selection.
+                if state == ElementState::Pressed {
+                    self.clear_selection();
+                }
+                if let Some(event) = self.mouse.button(state, button, self.modifiers) {
+                    self.send(Event::Mouse(event));
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                for event in self.mouse.scroll(delta, self.modifiers) {
+                    self.send(Event::Mouse(event));
+                }
+            }
+            WindowEvent::KeyboardInput { event, .. } => {
+                if let Some(shortcut) = shortcut(&event, self.modifiers) {
+                    if event.state == ElementState::Pressed {
+                        self.run_shortcut(shortcut);
+                    }
+                } else if let Some(key) = translate_key(&event, self.modifiers)
+                    && let Some(key) = self.key_for_tui(key)
+                {
+                    self.send(Event::Key(key));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::crossterm::event::{
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    };
+    use ratatui::crossterm::execute;
+
+    fn writer() -> (TerminalWriter, Arc<AtomicBool>) {
+        let fonts = Fonts::load(&Options::default().font).unwrap();
+        let terminal = create_terminal(Display::new(160, 64), &fonts);
+        let pending_draw = Arc::new(AtomicBool::new(false));
+        let terminal = Arc::new(Mutex::new(terminal));
+        let writer = TerminalWriter::new(terminal, pending_draw.clone(), None, None, None);
+        (writer, pending_draw)
+    }
+
+    /// Cells of the default font, `cols` by `rows` of them.
+    fn grid_display(cols: usize, rows: usize) -> Display {
+        let fonts = Fonts::load(&Options::default().font).unwrap();
+        let (cell_width, cell_height) = fonts.cell_size();
+        Display::new(cols * cell_width, rows * cell_height)
+    }
+
+    fn pixels(display: &Display) -> Vec<u32> {
+        display.buffer.iter().map(|pixel| pixel.load(Ordering::Relaxed)).collect()
+    }
+
+    /// What a terminal of the given size shows after the output.
+    fn rendered(output: &[u8], cols: usize, rows: usize) -> Vec<u32> {
+        let fonts = Fonts::load(&Options::default().font).unwrap();
+        let display = grid_display(cols, rows);
+        let mut terminal = create_terminal(display.clone(), &fonts);
+        terminal.process(output);
+        terminal.flush();
+        pixels(&display)
+    }
+
+    /// A terminal of the given size which has processed the output.
+    fn printed(output: &[u8], cols: usize, rows: usize) -> Terminal<Display> {
+        let fonts = Fonts::load(&Options::default().font).unwrap();
+        let mut terminal = create_terminal(grid_display(cols, rows), &fonts);
+        terminal.process(output);
+        terminal
+    }
+
+    /// Changes the terminal's size, and returns what it shows afterwards.
+    fn resize(terminal: &mut Terminal<Display>, cols: usize, rows: usize) -> Vec<u32> {
+        let display = grid_display(cols, rows);
+        terminal.set_display(display.clone());
+        terminal.flush();
+        pixels(&display)
+    }
+
+    #[test]
+    fn growing_keeps_the_screen() {
+        let mut terminal = printed(b"hello", 10, 4);
+        assert_eq!(resize(&mut terminal, 20, 8), rendered(b"hello", 20, 8));
+        assert_eq!((terminal.columns(), terminal.rows()), (20, 8));
+    }
+
+    #[test]
+    fn shrinking_keeps_the_cursor_line_in_view() {
+        let output = b"one\r\ntwo\r\nthree";
+        let mut terminal = printed(output, 10, 4);
+        assert_eq!(resize(&mut terminal, 10, 2), rendered(b"two\r\nthree", 10, 2));
+        // the line which went into the history comes back.
+        assert_eq!(resize(&mut terminal, 10, 4), rendered(output, 10, 4));
+    }
+
+    #[test]
+    fn resizing_keeps_the_alternate_screen() {
+        let mut terminal = printed(b"hello\x1b[?1049hfull", 10, 4);
+        resize(&mut terminal, 8, 3);
+        terminal.process(b"\x1b[?1049l");
+        terminal.flush();
+        assert_eq!(resize(&mut terminal, 8, 3), rendered(b"hello", 8, 3));
+    }
+
+    #[test]
+    fn redrawing_keeps_wide_characters() {
+        let fonts = Fonts::load(&Options::default().font).unwrap();
+        let theme = Theme::builtin().remove(0);
+        let display = grid_display(4, 1);
+        let mut canvas = PixelCanvas::new(display.clone(), &fonts, &theme);
+        canvas.cursor_visible = false;
+        // ratatui resets the cell a wide character covers, and never draws it.
+        canvas.set_cell(0, 0, &Cell::new("日"));
+        canvas.set_cell(1, 0, &Cell::new(" "));
+        canvas.dirty.retain(|position| position.x == 0);
+        canvas.flush();
+        let drawn = pixels(&display);
+        canvas.set_theme(&theme);
+        assert_eq!(pixels(&display), drawn);
+    }
+
+    #[test]
+    fn selections_copy_the_terminal_text() {
+        let terminal = printed("ab日c\r\nnext".as_bytes(), 10, 3);
+        let mut text = ScreenText::default();
+        copy_terminal_text(&terminal, &mut text);
+        let mut selector = Selector::default();
+        selector.press(Position::new(0, 0));
+        selector.drag(Position::new(9, 1));
+        assert_eq!(selector.text(&text).as_deref(), Some("ab日c\nnext"));
+    }
+
+    #[test]
+    fn the_cursor_is_out_of_view_while_the_history_is() {
+        let mut terminal = printed(b"1\r\n2\r\n3\r\n4", 10, 3);
+        assert_eq!(terminal.cursor_position(), Some((2, 1)));
+        terminal.handle_mouse(os_terminal::MouseInput::Scroll(1));
+        assert_eq!(terminal.cursor_position(), None);
+        terminal.handle_mouse(os_terminal::MouseInput::Scroll(-1));
+        assert_eq!(terminal.cursor_position(), Some((2, 1)));
+    }
+
+    #[test]
+    fn output_outside_of_updates_is_complete() {
+        assert_eq!(complete_frame_len(b""), 0);
+        assert_eq!(complete_frame_len(b"hello"), 5);
+        assert_eq!(complete_frame_len(b"\x1b[?25h"), 6);
+    }
+
+    #[test]
+    fn unterminated_update_is_held() {
+        let output = [b"before".as_slice(), BEGIN_SYNCHRONIZED_UPDATE, b"frame"].concat();
+        assert_eq!(complete_frame_len(&output), 6);
+        let output = [BEGIN_SYNCHRONIZED_UPDATE, b"frame", END_SYNCHRONIZED_UPDATE].concat();
+        assert_eq!(complete_frame_len(&output), output.len());
+    }
+
+    #[test]
+    fn update_cut_off_at_the_start_is_held() {
+        for len in 1..BEGIN_SYNCHRONIZED_UPDATE.len() {
+            let output = [b"ab".as_slice(), &BEGIN_SYNCHRONIZED_UPDATE[..len]].concat();
+            assert_eq!(complete_frame_len(&output), 2);
+        }
+    }
+
+    #[test]
+    fn repeated_begin_ends_at_the_first_end() {
+        let begin = BEGIN_SYNCHRONIZED_UPDATE;
+        let end = END_SYNCHRONIZED_UPDATE;
+        let output = [begin, begin, b"frame", end, b"after"].concat();
+        assert_eq!(complete_frame_len(&output), output.len());
+        let output = [begin, b"one", end, b"between", begin, b"two"].concat();
+        let complete = [begin, b"one", end, b"between"].concat().len();
+        assert_eq!(complete_frame_len(&output), complete);
+    }
+
+    #[test]
+    fn partial_frame_is_never_presented() {
+        let (mut writer, pending_draw) = writer();
+        writer.write_all(b"\x1b[?20").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(b"26hhalf a ").unwrap();
+        writer.flush().unwrap();
+        assert!(!pending_draw.load(Ordering::Relaxed));
+        writer.write_all(b"frame").unwrap();
+        writer.write_all(END_SYNCHRONIZED_UPDATE).unwrap();
+        writer.flush().unwrap();
+        assert!(pending_draw.load(Ordering::Relaxed));
+        assert!(writer.output.is_empty());
+    }
+
+    #[test]
+    fn output_before_an_update_is_presented() {
+        let (mut writer, pending_draw) = writer();
+        writer.write_all(b"shown").unwrap();
+        writer.write_all(BEGIN_SYNCHRONIZED_UPDATE).unwrap();
+        writer.write_all(b"held").unwrap();
+        writer.flush().unwrap();
+        assert!(pending_draw.load(Ordering::Relaxed));
+        let held = [BEGIN_SYNCHRONIZED_UPDATE, b"held"].concat();
+        assert_eq!(writer.output, held);
+    }
+
+    fn backend() -> VirtualBackend<io::Sink> {
+        let size = Arc::new(Mutex::new(ScreenSize::new(10, 4, (1, 1))));
+        VirtualBackend::new(CrosstermBackend::new(io::sink()), size)
+    }
+
+    #[test]
+    fn the_backend_follows_the_cursor() {
+        let mut backend = backend();
+        let cell = Cell::new("a");
+        let wide = Cell::new("日");
+        backend.draw([(2, 1, &cell), (4, 1, &wide)].into_iter()).unwrap();
+        // just after the last cell drawn, which was two columns wide.
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::new(6, 1));
+
+        backend.set_cursor_position(Position::new(8, 0)).unwrap();
+        backend.append_lines(2).unwrap();
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::new(8, 2));
+        // the cursor stays on the last line while the terminal scrolls.
+        backend.append_lines(5).unwrap();
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::new(8, 3));
+
+        backend.clear().unwrap();
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::ORIGIN);
+    }
+
+    #[test]
+    fn the_backend_keeps_the_cursor_on_the_grid() {
+        let mut backend = backend();
+        backend.set_cursor_position(Position::new(20, 9)).unwrap();
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::new(9, 3));
+        let cell = Cell::new("a");
+        backend.draw([(9, 0, &cell)].into_iter()).unwrap();
+        assert_eq!(backend.get_cursor_position().unwrap(), Position::new(9, 0));
+    }
+
+    #[test]
+    fn the_backend_follows_the_keyboard_flags_the_tui_pushes() {
+        let modes = Arc::new(Mutex::new(ModeScanner::new()));
+        let mut backend = backend().with_modes(modes.clone());
+        let report = || modes.lock().unwrap().modes().report_event_types;
+
+        let flags = KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+        execute!(backend, PushKeyboardEnhancementFlags(flags)).unwrap();
+        assert!(report());
+        execute!(backend, PopKeyboardEnhancementFlags).unwrap();
+        assert!(!report());
+    }
+
+    #[test]
+    fn size_queries_in_the_window_are_left_to_the_backend() {
+        // a TUI learns its size in pixels from the backend, as it would from crossterm. A reply
+        // would reach it as input it can't read.
+        let fonts = Fonts::load(&Options::default().font).unwrap();
+        let mut terminal = create_terminal(grid_display(10, 4), &fonts);
+        let (input_tx, input_rx) = channel();
+        let _decoder = forward_pty_input(&mut terminal, input_tx);
+        let size = Arc::new(Mutex::new(ScreenSize::new(10, 4, fonts.cell_size())));
+        let pending_draw = Arc::new(AtomicBool::new(false));
+        let terminal = Arc::new(Mutex::new(terminal));
+        let writer = TerminalWriter::new(terminal, pending_draw.clone(), None, None, None);
+        let screen = GUIScreen {
+            input_rx,
+            size: size.clone(),
+            recorder: None,
+            overlays: Arc::default(),
+            pending_draw,
+            text: Arc::default(),
+            shown_cursor: Arc::default(),
+            modes: Arc::new(Mutex::new(ModeScanner::new())),
+        };
+        let mut backend = Screen::create_backend(&screen, writer);
+        backend.write_all(b"\x1b[14t\x1b[16t").unwrap();
+        Backend::flush(&mut backend).unwrap();
+
+        let event = Screen::<TerminalWriter>::poll_and_read(&screen, Duration::ZERO).unwrap();
+        assert!(event.is_none());
+        let pixels = size.lock().unwrap().pixels();
+        assert_eq!(backend.window_size().unwrap().pixels, pixels);
+    }
+
+    #[test]
+    fn update_which_does_not_end_is_shown_after_a_while() {
+        let (mut writer, pending_draw) = writer();
+        writer.write_all(BEGIN_SYNCHRONIZED_UPDATE).unwrap();
+        writer.write_all(b"held").unwrap();
+        writer.flush().unwrap();
+        assert!(!pending_draw.load(Ordering::Relaxed));
+        assert!(writer.held_timeout().unwrap() > Duration::ZERO);
+
+        writer.held_since = Some(Instant::now() - SYNCHRONIZED_UPDATE_TIMEOUT);
+        assert_eq!(writer.held_timeout(), Some(Duration::ZERO));
+        writer.flush().unwrap();
+        assert!(pending_draw.load(Ordering::Relaxed));
+        assert!(writer.output.is_empty());
+        assert_eq!(writer.held_timeout(), None);
+    }
+
+    #[test]
+    fn update_which_grows_too_big_is_shown() {
+        let (mut writer, pending_draw) = writer();
+        writer.write_all(BEGIN_SYNCHRONIZED_UPDATE).unwrap();
+        writer.write_all(&vec![b' '; MAX_SYNCHRONIZED_UPDATE]).unwrap();
+        writer.flush().unwrap();
+        assert!(pending_draw.load(Ordering::Relaxed));
+        assert!(writer.output.is_empty());
+    }
+
+    #[test]
+    fn each_update_is_held_for_its_own_time() {
+        let (mut writer, _) = writer();
+        writer.write_all(BEGIN_SYNCHRONIZED_UPDATE).unwrap();
+        writer.flush().unwrap();
+        let long_ago = Instant::now() - SYNCHRONIZED_UPDATE_TIMEOUT / 2;
+        writer.held_since = Some(long_ago);
+        writer.write_all(END_SYNCHRONIZED_UPDATE).unwrap();
+        writer.write_all(BEGIN_SYNCHRONIZED_UPDATE).unwrap();
+        writer.flush().unwrap();
+        assert!(writer.held_since.unwrap() > long_ago);
+        assert_eq!(writer.output, BEGIN_SYNCHRONIZED_UPDATE);
+    }
+}
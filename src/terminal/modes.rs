@@ -0,0 +1,167 @@
+/// How much mouse activity a program has asked to be told about.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub(crate) enum MouseTracking {
+    #[default]
+    Off,
+    /// Mode 1000, button presses, releases and the wheel.
+    Buttons,
+    /// Mode 1002, also motion while a button is held.
+    Drag,
+    /// Mode 1003, all motion.
+    Motion,
+}
+
+/// Private modes which affect how input has to be encoded. os-terminal keeps track of these
+/// internally, but doesn't expose them, so they are followed separately from the same output.
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Modes {
+    /// Mode 1, cursor keys send `ESC O` instead of `ESC [`.
+    pub(crate) application_cursor: bool,
+    pub(crate) mouse: MouseTracking,
+    /// Mode 1006, mouse reports use the SGR encoding.
+    pub(crate) sgr_mouse: bool,
+    /// Mode 2004, pasted text is wrapped in brackets.
+    pub(crate) bracketed_paste: bool,
+    /// Modes 47, 1047 and 1049, the program is drawing on the alternate screen.
+    pub(crate) alternate_screen: bool,
+    /// The keyboard enhancement flags the program last pushed include reporting event types,
+    /// so it wants to be told about key repeats and releases.
+    pub(crate) report_event_types: bool,
+}
+
+impl Modes {
+    fn set(&mut self, mode: u16, enabled: bool) {
+        let mouse = |tracking| if enabled { tracking } else { MouseTracking::Off };
+        match mode {
+            1 => self.application_cursor = enabled,
+            1000 => self.mouse = mouse(MouseTracking::Buttons),
+            1002 => self.mouse = mouse(MouseTracking::Drag),
+            1003 => self.mouse = mouse(MouseTracking::Motion),
+            1006 => self.sgr_mouse = enabled,
+            2004 => self.bracketed_paste = enabled,
+            47 | 1047 | 1049 => self.alternate_screen = enabled,
+            _ => {}
+        }
+    }
+}
+
+/// The keyboard enhancement flag which asks for key repeats and releases to be reported.
+const REPORT_EVENT_TYPES: u16 = 2;
+
+/// How many keyboard enhancement flags are kept, pushing more forgets the oldest.
+const MAX_KEYBOARD_FLAGS: usize = 16;
+
+enum State {
+    Ground,
+    Escape,
+    Csi,
+    /// Inside `ESC [` followed by `?`, `>` or `<`, collecting the parameters.
+    Params(u8, Vec<u16>, Option<u16>),
+}
+
+/// Follows the set and reset private mode sequences (`ESC [ ? Pm h` and `ESC [ ? Pm l`) in a
+/// stream of output, and the keyboard enhancement flags pushed with `ESC [ > flags u` and popped
+/// with `ESC [ < count u`. Sequences may be split across calls.
+pub(crate) struct ModeScanner {
+    modes: Modes,
+    state: State,
+    keyboard_flags: Vec<u16>,
+}
+
+impl ModeScanner {
+    pub(crate) fn new() -> Self {
+        Self {
+            modes: Modes::default(),
+            state: State::Ground,
+            keyboard_flags: Vec::new(),
+        }
+    }
+
+    pub(crate) fn modes(&self) -> Modes {
+        self.modes
+    }
+
+    pub(crate) fn scan(&mut self, output: &[u8]) {
+        for byte in output {
+            self.state = match (std::mem::replace(&mut self.state, State::Ground), byte) {
+                (_, 0x1b) => State::Escape,
+                (State::Escape, b'[') => State::Csi,
+                (State::Csi, b'?' | b'>' | b'<') => State::Params(*byte, Vec::new(), None),
+                (State::Params(marker, params, current), b'0'..=b'9') => {
+                    let digit = (byte - b'0') as u16;
+                    let current = current.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+                    State::Params(marker, params, Some(current))
+                }
+                (State::Params(marker, mut params, current), b';') => {
+                    params.extend(current);
+                    State::Params(marker, params, None)
+                }
+                (State::Params(b'?', mut params, current), b'h' | b'l') => {
+                    params.extend(current);
+                    for mode in params {
+                        self.modes.set(mode, *byte == b'h');
+                    }
+                    State::Ground
+                }
+                (State::Params(b'>', params, current), b'u') => {
+                    if self.keyboard_flags.len() == MAX_KEYBOARD_FLAGS {
+                        self.keyboard_flags.remove(0);
+                    }
+                    self.keyboard_flags.push(params.first().copied().or(current).unwrap_or(0));
+                    self.update_keyboard();
+                    State::Ground
+                }
+                (State::Params(b'<', params, current), b'u') => {
+                    let count = params.first().copied().or(current).unwrap_or(1) as usize;
+                    let remaining = self.keyboard_flags.len().saturating_sub(count);
+                    self.keyboard_flags.truncate(remaining);
+                    self.update_keyboard();
+                    State::Ground
+                }
+                _ => State::Ground,
+            };
+        }
+    }
+
+    fn update_keyboard(&mut self) {
+        let flags = self.keyboard_flags.last().copied().unwrap_or(0);
+        self.modes.report_event_types = flags & REPORT_EVENT_TYPES != 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(output: &[&[u8]]) -> Modes {
+        let mut scanner = ModeScanner::new();
+        for output in output {
+            scanner.scan(output);
+        }
+        scanner.modes()
+    }
+
+    #[test]
+    fn private_modes_are_followed_across_writes() {
+        let modes = scan(&[b"\x1b[?1;10", b"06h\x1b[?1l"]);
+        assert!(!modes.application_cursor);
+        assert!(modes.sgr_mouse);
+    }
+
+    #[test]
+    fn keyboard_flags_report_event_types_until_popped() {
+        assert!(scan(&[b"\x1b[>3u"]).report_event_types);
+        assert!(!scan(&[b"\x1b[>1u"]).report_event_types);
+        assert!(!scan(&[b"\x1b[>2u\x1b[<u"]).report_event_types);
+        // popping goes back to the flags pushed before.
+        assert!(scan(&[b"\x1b[>2u\x1b[>1u\x1b[<1u"]).report_event_types);
+        assert!(!scan(&[b"\x1b[>2u\x1b[>1u\x1b[<2u"]).report_event_types);
+    }
+
+    #[test]
+    fn other_sequences_leave_the_keyboard_flags_alone() {
+        // modifyOtherKeys and the secondary device attributes query.
+        assert!(!scan(&[b"\x1b[>4;2m\x1b[>c\x1b[>2"]).report_event_types);
+        assert!(!scan(&[b"\x1b[?2u"]).report_event_types);
+    }
+}
@@ -0,0 +1,126 @@
+use ratatui::crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Position;
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, MouseButton as WinitMouseButton, MouseScrollDelta};
+use winit::keyboard::ModifiersState;
+
+use crate::terminal::keyboard::to_key_modifiers;
+
+const TOUCHPAD_SCROLL_MULTIPLIER: f32 = 0.25;
+
+/// Turns winit's pixel based mouse events into crossterm mouse events on the cell grid.
+pub(super) struct MouseTracker {
+    cell: Option<(u16, u16)>,
+    pressed: Option<MouseButton>,
+    scroll_accumulator: f32,
+}
+
+impl MouseTracker {
+    pub(super) fn new() -> Self {
+        Self {
+            cell: None,
+            pressed: None,
+            scroll_accumulator: 0.0,
+        }
+    }
+
+    fn event(&self, kind: MouseEventKind, modifiers: ModifiersState) -> Option<MouseEvent> {
+        let (column, row) = self.cell?;
+        Some(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: to_key_modifiers(modifiers),
+        })
+    }
+
+    /// Returns an event only when the pointer moves to a different cell.
+    pub(super) fn moved(
+        &mut self,
+        position: PhysicalPosition<f64>,
+        cell_size: (usize, usize),
+        grid_size: (u16, u16),
+        modifiers: ModifiersState,
+    ) -> Option<MouseEvent> {
+        let (cell_width, cell_height) = cell_size;
+        let (cols, rows) = grid_size;
+        let column = position.x.max(0.0) as usize / cell_width;
+        let row = position.y.max(0.0) as usize / cell_height;
+        let column = column.min(cols.saturating_sub(1) as usize);
+        let row = row.min(rows.saturating_sub(1) as usize);
+        let cell = Some((column as u16, row as u16));
+        if cell == self.cell {
+            return None;
+        }
+        self.cell = cell;
+
+        let kind = match self.pressed {
+            Some(button) => MouseEventKind::Drag(button),
+            None => MouseEventKind::Moved,
+        };
+        self.event(kind, modifiers)
+    }
+
+    /// Returns the cell the pointer is over, none if it is outside of the window.
+    pub(super) fn cell(&self) -> Option<Position> {
+        self.cell.map(|(column, row)| Position::new(column, row))
+    }
+
+    pub(super) fn left(&mut self) {
+        self.cell = None;
+    }
+
+    pub(super) fn button(
+        &mut self,
+        state: ElementState,
+        button: WinitMouseButton,
+        modifiers: ModifiersState,
+    ) -> Option<MouseEvent> {
+        let button = match button {
+            WinitMouseButton::Left => MouseButton::Left,
+            WinitMouseButton::Right => MouseButton::Right,
+            WinitMouseButton::Middle => MouseButton::Middle,
+            _ => return None,
+        };
+        let kind = match state {
+            ElementState::Pressed => {
+                self.pressed = Some(button);
+                MouseEventKind::Down(button)
+            }
+            ElementState::Released => {
+                if self.pressed == Some(button) {
+                    self.pressed = None;
+                }
+                MouseEventKind::Up(button)
+            }
+        };
+        self.event(kind, modifiers)
+    }
+
+    /// Touchpads report scrolling in pixels, which is accumulated until it adds up to whole
+    /// lines. Every line scrolled produces one event.
+    pub(super) fn scroll(
+        &mut self,
+        delta: MouseScrollDelta,
+        modifiers: ModifiersState,
+    ) -> Vec<MouseEvent> {
+        self.scroll_accumulator += match delta {
+            MouseScrollDelta::LineDelta(_, lines) => lines,
+            MouseScrollDelta::PixelDelta(delta) => delta.y as f32 * TOUCHPAD_SCROLL_MULTIPLIER,
+        };
+        if self.scroll_accumulator.abs() < 1.0 {
+            return Vec::new();
+        }
+
+        let lines = self.scroll_accumulator as isize;
+        self.scroll_accumulator -= lines as f32;
+        let kind = if lines > 0 {
+            MouseEventKind::ScrollUp
+        } else {
+            MouseEventKind::ScrollDown
+        };
+        (0..lines.unsigned_abs())
+            .filter_map(|_| self.event(kind, modifiers))
+            .collect()
+    }
+}
@@ -0,0 +1,325 @@
+use crate::terminal::clipboard::{Clipboard, PasteSource};
+use crate::terminal::{ReplyWriter, log};
+
+/// The most text a single copy can put on the clipboard, in bytes.
+const MAX_COPY_SIZE: usize = 1 << 20;
+// the copied text is base64 encoded, which takes four bytes for every three, and comes after the
+// selections it is copied to, of which there are only a dozen to name. Sequences are only held
+// up to this size, the text in them is checked against the limit once it's decoded.
+const MAX_SEQUENCE_SIZE: usize = MAX_COPY_SIZE.div_ceil(3) * 4 + 16;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, &byte)| {
+            bits | (byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(BASE64[((bits >> (18 - 6 * index)) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn base64_decode(encoded: &[u8]) -> Option<Vec<u8>> {
+    let encoded = encoded
+        .strip_suffix(b"==")
+        .or_else(|| encoded.strip_suffix(b"="))
+        .unwrap_or(encoded);
+    let mut decoded = Vec::with_capacity(encoded.len() / 4 * 3);
+    for chunk in encoded.chunks(4) {
+        if chunk.len() == 1 {
+            return None;
+        }
+        let mut bits = 0u32;
+        for (index, &byte) in chunk.iter().enumerate() {
+            let value = BASE64.iter().position(|&c| c == byte)? as u32;
+            bits |= value << (18 - 6 * index);
+        }
+        decoded.extend_from_slice(&bits.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(decoded)
+}
+
+enum State {
+    Ground,
+    Escape,
+    /// Inside `ESC ]`, holding the bytes until it's clear whether this is OSC 52.
+    OscNumber(Vec<u8>),
+    /// Inside a control string which isn't OSC 52, passed on until its terminator.
+    Passthrough,
+    /// Inside OSC 52, with whether it got too long to keep.
+    Clipboard(Vec<u8>, bool),
+    ClipboardEscape(Vec<u8>, bool),
+}
+
+/// Takes OSC 52 clipboard sequences out of the output and carries them out on the system
+/// clipboard. os-terminal handles them itself, but drops any longer than its 1024 byte limit
+/// for control strings, which most copies are.
+///
+/// Queries are only answered when there is somewhere to reply to. A TUI has no way of reading
+/// the reply, so only programs run in a pseudo-terminal, which are allowed to read the
+/// clipboard, are given one.
+pub(crate) struct ClipboardFilter {
+    clipboard: Clipboard,
+    reply: Option<ReplyWriter>,
+    state: State,
+}
+
+impl ClipboardFilter {
+    pub(crate) fn new(clipboard: Clipboard, reply: Option<ReplyWriter>) -> Self {
+        Self {
+            clipboard,
+            reply,
+            state: State::Ground,
+        }
+    }
+
+    /// Appends the output to `filtered`, without any OSC 52 sequences.
+    pub(crate) fn filter(&mut self, output: &[u8], filtered: &mut Vec<u8>) {
+        for &byte in output {
+            self.state = match (std::mem::replace(&mut self.state, State::Ground), byte) {
+                (State::Clipboard(data, too_long), 0x07) => {
+                    self.run(&data, too_long, "\x07");
+                    State::Ground
+                }
+                (State::Clipboard(data, too_long), 0x1b) => State::ClipboardEscape(data, too_long),
+                (State::Clipboard(mut data, too_long), _) => {
+                    let too_long = too_long || data.len() >= MAX_SEQUENCE_SIZE;
+                    if !too_long {
+                        data.push(byte);
+                    }
+                    State::Clipboard(data, too_long)
+                }
+                (State::ClipboardEscape(data, too_long), b'\\') => {
+                    self.run(&data, too_long, "\x1b\\");
+                    State::Ground
+                }
+                // a sequence which isn't terminated properly is dropped.
+                (State::ClipboardEscape(..), _) => self.escape(byte, filtered),
+                (State::OscNumber(mut held), b'0'..=b'9') => {
+                    held.push(byte);
+                    State::OscNumber(held)
+                }
+                (State::OscNumber(held), b';') if held[2..] == *b"52" => {
+                    State::Clipboard(Vec::new(), false)
+                }
+                (State::OscNumber(held), _) => {
+                    filtered.extend_from_slice(&held);
+                    self.passthrough(byte, filtered)
+                }
+                (State::Passthrough, _) => self.passthrough(byte, filtered),
+                (State::Escape, _) => self.escape(byte, filtered),
+                (State::Ground, 0x1b) => State::Escape,
+                (State::Ground, _) => {
+                    filtered.push(byte);
+                    State::Ground
+                }
+            };
+        }
+    }
+
+    /// Handles the byte after an escape, which is held back in case it starts OSC 52.
+    fn escape(&mut self, byte: u8, filtered: &mut Vec<u8>) -> State {
+        match byte {
+            b']' => State::OscNumber(vec![0x1b, byte]),
+            0x1b => {
+                filtered.push(0x1b);
+                State::Escape
+            }
+            _ => {
+                filtered.extend_from_slice(&[0x1b, byte]);
+                State::Ground
+            }
+        }
+    }
+
+    fn passthrough(&mut self, byte: u8, filtered: &mut Vec<u8>) -> State {
+        match byte {
+            0x1b => State::Escape,
+            0x07 => {
+                filtered.push(byte);
+                State::Ground
+            }
+            _ => {
+                filtered.push(byte);
+                State::Passthrough
+            }
+        }
+    }
+
+    /// Carries out an OSC 52 sequence, `selection;data` where the data is base64 encoded text,
+    /// or `?` to ask for the clipboard. Every selection is the clipboard.
+    fn run(&mut self, data: &[u8], too_long: bool, terminator: &str) {
+        let dropped = || {
+            log(format_args!(
+                "a copy of more than {} bytes was dropped",
+                MAX_COPY_SIZE
+            ))
+        };
+        if too_long {
+            dropped();
+            return;
+        }
+        let Some(separator) = data.iter().position(|&byte| byte == b';') else {
+            return;
+        };
+        let (selection, payload) = (&data[..separator], &data[separator + 1..]);
+        if payload == b"?" {
+            let Some(reply) = &self.reply else {
+                return;
+            };
+            let text = self
+                .clipboard
+                .paste(PasteSource::Clipboard)
+                .unwrap_or_default();
+            let selection = String::from_utf8_lossy(selection);
+            let encoded = base64_encode(text.as_bytes());
+            reply(format!("\x1b]52;{};{}{}", selection, encoded, terminator).as_bytes());
+        } else if let Some(text) =
+            base64_decode(payload).and_then(|text| String::from_utf8(text).ok())
+        {
+            if text.len() > MAX_COPY_SIZE {
+                dropped();
+            } else {
+                self.clipboard.copy(text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{Receiver, channel};
+
+    use super::*;
+    use crate::terminal::clipboard::MemoryClipboard;
+
+    fn clipboard(text: Option<&str>) -> Clipboard {
+        let clipboard = Clipboard::with_backend(Box::<MemoryClipboard>::default());
+        if let Some(text) = text {
+            clipboard.copy(text.to_string());
+        }
+        clipboard
+    }
+
+    fn replies() -> (ReplyWriter, Receiver<Vec<u8>>) {
+        let (tx, rx) = channel();
+        (Box::new(move |data| tx.send(data.to_vec()).unwrap()), rx)
+    }
+
+    fn filter(filter: &mut ClipboardFilter, output: &[u8]) -> Vec<u8> {
+        let mut filtered = Vec::new();
+        filter.filter(output, &mut filtered);
+        filtered
+    }
+
+    #[test]
+    fn base64_round_trips() {
+        #[rustfmt::skip]
+        let cases: [(&[u8], &str); 7] = [
+            (b"", ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"fooba", "Zm9vYmE="),
+            (b"\xff\xfe\x00?>", "//4APz4="),
+        ];
+        for (data, encoded) in cases {
+            assert_eq!(base64_encode(data), encoded);
+            assert_eq!(base64_decode(encoded.as_bytes()).as_deref(), Some(data));
+        }
+    }
+
+    #[test]
+    fn base64_rejects_malformed_data() {
+        assert_eq!(base64_decode(b"Zm9vY"), None);
+        assert_eq!(base64_decode(b"Zm9v!A=="), None);
+        assert_eq!(base64_decode(b"Zm 9v"), None);
+    }
+
+    #[test]
+    fn copies_to_the_clipboard() {
+        let clipboard = clipboard(None);
+        let mut clipboard_filter = ClipboardFilter::new(clipboard.clone(), None);
+        let output = b"a\x1b]52;c;aGVsbG8=\x07b\x1b]52;;d29ybGQ=\x1b\\c";
+        assert_eq!(filter(&mut clipboard_filter, output), b"abc");
+        assert_eq!(clipboard.paste(PasteSource::Clipboard).as_deref(), Some("world"));
+    }
+
+    #[test]
+    fn copies_split_across_output() {
+        let clipboard = clipboard(None);
+        let mut clipboard_filter = ClipboardFilter::new(clipboard.clone(), None);
+        let mut filtered = Vec::new();
+        for chunk in [&b"\x1b"[..], b"]5", b"2;c;aGV", b"sbG8=\x1b", b"\\x"] {
+            filtered.extend(filter(&mut clipboard_filter, chunk));
+        }
+        assert_eq!(filtered, b"x");
+        assert_eq!(clipboard.paste(PasteSource::Clipboard).as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn passes_other_strings_through() {
+        let clipboard = clipboard(Some("kept"));
+        let mut clipboard_filter = ClipboardFilter::new(clipboard.clone(), None);
+        let output = b"\x1b]0;title\x07\x1b]8;;https://example.com\x1b\\\x1b]5;x\x07\x1b[1m";
+        assert_eq!(filter(&mut clipboard_filter, output), output);
+        assert_eq!(clipboard.paste(PasteSource::Clipboard).as_deref(), Some("kept"));
+    }
+
+    #[test]
+    fn answers_reads_with_the_same_terminator() {
+        let (reply, replies) = replies();
+        let mut clipboard_filter = ClipboardFilter::new(clipboard(Some("hi")), Some(reply));
+        let output = b"\x1b]52;c;?\x07\x1b]52;p;?\x1b\\";
+        assert_eq!(filter(&mut clipboard_filter, output), b"");
+        assert_eq!(replies.try_recv().unwrap(), b"\x1b]52;c;aGk=\x07");
+        assert_eq!(replies.try_recv().unwrap(), b"\x1b]52;p;aGk=\x1b\\");
+    }
+
+    #[test]
+    fn reads_of_an_empty_clipboard_are_answered_empty() {
+        let (reply, replies) = replies();
+        let mut clipboard_filter = ClipboardFilter::new(clipboard(None), Some(reply));
+        filter(&mut clipboard_filter, b"\x1b]52;c;?\x07");
+        assert_eq!(replies.try_recv().unwrap(), b"\x1b]52;c;\x07");
+    }
+
+    #[test]
+    fn reads_are_not_answered_without_a_reply() {
+        let clipboard = clipboard(Some("secret"));
+        let mut clipboard_filter = ClipboardFilter::new(clipboard.clone(), None);
+        assert_eq!(filter(&mut clipboard_filter, b"\x1b]52;c;?\x07"), b"");
+        assert_eq!(clipboard.paste(PasteSource::Clipboard).as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn copies_up_to_the_size_limit() {
+        let text = "x".repeat(MAX_COPY_SIZE);
+        let output = format!("\x1b]52;c;{}\x07", base64_encode(text.as_bytes()));
+        let clipboard = clipboard(None);
+        let mut clipboard_filter = ClipboardFilter::new(clipboard.clone(), None);
+        filter(&mut clipboard_filter, output.as_bytes());
+        assert_eq!(clipboard.paste(PasteSource::Clipboard), Some(text));
+    }
+
+    #[test]
+    fn drops_copies_over_the_size_limit() {
+        for size in [MAX_COPY_SIZE + 1, MAX_COPY_SIZE * 2] {
+            let text = "x".repeat(size);
+            let output = format!("a\x1b]52;c;{}\x07b", base64_encode(text.as_bytes()));
+            let clipboard = clipboard(Some("kept"));
+            let mut clipboard_filter = ClipboardFilter::new(clipboard.clone(), None);
+            assert_eq!(filter(&mut clipboard_filter, output.as_bytes()), b"ab");
+            assert_eq!(clipboard.paste(PasteSource::Clipboard).as_deref(), Some("kept"));
+        }
+    }
+}
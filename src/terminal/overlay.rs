@@ -0,0 +1,341 @@
+use std::cell::RefCell;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use ratatui::buffer::Buffer;
+use ratatui::layout::Rect;
+use ratatui::text::Text;
+use ratatui::widgets::{Clear, Widget};
+
+use crate::terminal::ScreenSize;
+use crate::theme::Rgb;
+
+/// The pixels of a [`PixelOverlay`], covering its area of cells exactly. They start out black.
+#[derive(PartialEq)]
+pub struct Pixels {
+    width: usize,
+    height: usize,
+    data: Vec<u32>,
+}
+
+impl Pixels {
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the color of a pixel, none if it is outside of the overlay.
+    pub fn get(&self, x: usize, y: usize) -> Option<Rgb> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let color = self.data[y * self.width + x];
+        Some(((color >> 16) as u8, (color >> 8) as u8, color as u8))
+    }
+
+    /// Changes the color of a pixel. Pixels outside of the overlay are ignored, so shapes can
+    /// be drawn without clipping them first.
+    pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            self.data[y * self.width + x] = to_pixel(color);
+        }
+    }
+
+    pub fn fill(&mut self, color: Rgb) {
+        self.data.fill(to_pixel(color));
+    }
+}
+
+/// A pixel which shows what is underneath it. [`Pixels`] are always opaque, only images the
+/// TUI writes to the terminal can have transparent pixels.
+pub(crate) const TRANSPARENT: u32 = 0xff00_0000;
+
+fn to_pixel(color: Rgb) -> u32 {
+    (color.0 as u32) << 16 | (color.1 as u32) << 8 | color.2 as u32
+}
+
+/// A widget which paints pixels over the cells it is rendered into, for images, plots and
+/// anything else finer than a cell. The pixels are only drawn in the window, the cells
+/// underneath hold the placeholder, which is what [`run_in_terminal`](crate::run_in_terminal)
+/// shows instead.
+///
+/// The paint function is called every time the widget is rendered, with pixels sized to the
+/// area at the current font size.
+pub struct PixelOverlay<'a, F> {
+    paint: F,
+    placeholder: Text<'a>,
+}
+
+impl<'a, F: FnOnce(&mut Pixels)> PixelOverlay<'a, F> {
+    pub fn new(paint: F) -> Self {
+        Self {
+            paint,
+            placeholder: Text::default(),
+        }
+    }
+
+    /// The text shown in the middle of the area where the pixels can't be drawn.
+    pub fn placeholder(mut self, placeholder: impl Into<Text<'a>>) -> Self {
+        self.placeholder = placeholder.into();
+        self
+    }
+}
+
+impl<F: FnOnce(&mut Pixels)> Widget for PixelOverlay<'_, F> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let area = area.intersection(buf.area);
+        Clear.render(area, buf);
+        let height = (self.placeholder.height() as u16).min(area.height);
+        let top = area.y + (area.height - height) / 2;
+        self.placeholder
+            .centered()
+            .render(Rect::new(area.x, top, area.width, height), buf);
+
+        FRAME.with_borrow_mut(|frame| {
+            if let Some(frame) = frame {
+                frame.add(area, self.paint);
+            }
+        });
+    }
+}
+
+/// Pixels placed over the display, in pixel coordinates.
+#[derive(PartialEq)]
+struct Overlay {
+    x: usize,
+    y: usize,
+    pixels: Pixels,
+}
+
+impl Overlay {
+    fn rows(&self) -> Range<usize> {
+        self.y..self.y + self.pixels.height
+    }
+}
+
+/// Pixels shown inverted, one row of selected cells.
+#[derive(PartialEq)]
+struct Highlight {
+    x: Range<usize>,
+    y: Range<usize>,
+}
+
+/// What the window lays over the display when it presents it: images written to the terminal,
+/// above them the overlays of the last frame the TUI drew, then text being composed with an
+/// input method, and the text selected in the window on top. The display itself is left as the
+/// terminal or canvas drew it, so the cells underneath reappear as soon as an overlay goes away.
+#[derive(Default)]
+pub(crate) struct OverlayLayer {
+    /// Images with the cells they cover, which stay until those cells are written to.
+    images: Vec<(Rect, Overlay)>,
+    overlays: Vec<Overlay>,
+    preedit: Option<Overlay>,
+    selection: Vec<Highlight>,
+    /// Rows which have to be presented again because an overlay on them changed.
+    damage: Vec<Range<usize>>,
+}
+
+impl OverlayLayer {
+    fn set(&mut self, overlays: Vec<Overlay>) -> bool {
+        if overlays == self.overlays {
+            return false;
+        }
+        let old = std::mem::replace(&mut self.overlays, overlays);
+        self.damage
+            .extend(old.iter().chain(&self.overlays).map(Overlay::rows));
+        true
+    }
+
+    /// Places an image with its top left corner at the top left of a cell.
+    pub(crate) fn add_image(
+        &mut self,
+        column: usize,
+        row: usize,
+        size: ScreenSize,
+        width: usize,
+        height: usize,
+        data: Vec<u32>,
+    ) {
+        let (cell_width, cell_height) = (size.cell_width as usize, size.cell_height as usize);
+        if cell_width == 0 || cell_height == 0 {
+            return;
+        }
+        let cells = Rect::new(
+            column as u16,
+            row as u16,
+            width.div_ceil(cell_width) as u16,
+            height.div_ceil(cell_height) as u16,
+        );
+        let image = Overlay {
+            x: column * cell_width,
+            y: row * cell_height,
+            pixels: Pixels {
+                width,
+                height,
+                data,
+            },
+        };
+        // an image drawn over another one replaces it.
+        self.erase_images(cells);
+        self.damage.push(image.rows());
+        self.images.push((cells, image));
+    }
+
+    /// Removes the images covering any of the given cells.
+    pub(crate) fn erase_images(&mut self, cells: Rect) {
+        let damage = &mut self.damage;
+        self.images.retain(|(covered, image)| {
+            let erased = covered.intersects(cells);
+            if erased {
+                damage.push(image.rows());
+            }
+            !erased
+        });
+    }
+
+    /// Shows the text being composed with its top left corner at the given pixel, or stops
+    /// showing it. Returns whether anything changed.
+    pub(crate) fn set_preedit(&mut self, x: usize, y: usize, pixels: Option<Pixels>) -> bool {
+        let preedit = pixels.map(|pixels| Overlay { x, y, pixels });
+        if preedit == self.preedit {
+            return false;
+        }
+        let old = std::mem::replace(&mut self.preedit, preedit);
+        self.damage
+            .extend(old.iter().chain(&self.preedit).map(Overlay::rows));
+        true
+    }
+
+    /// Highlights the selected cells, given as rectangles of cells `cell_size` pixels in size.
+    /// Returns whether anything changed.
+    pub(crate) fn set_selection(&mut self, cells: &[Rect], cell_size: (usize, usize)) -> bool {
+        let (cell_width, cell_height) = cell_size;
+        let selection = cells
+            .iter()
+            .map(|cells| Highlight {
+                x: cells.left() as usize * cell_width..cells.right() as usize * cell_width,
+                y: cells.top() as usize * cell_height..cells.bottom() as usize * cell_height,
+            })
+            .collect::<Vec<_>>();
+        if selection == self.selection {
+            return false;
+        }
+        let old = std::mem::replace(&mut self.selection, selection);
+        self.damage.extend(
+            old.iter()
+                .chain(&self.selection)
+                .map(|highlight| highlight.y.clone()),
+        );
+        true
+    }
+
+    /// Returns the rows damaged by overlays since the last call.
+    pub(crate) fn take_damage(&mut self) -> Vec<Range<usize>> {
+        std::mem::take(&mut self.damage)
+    }
+
+    /// Lays the overlays over the given rows of a frame, which is `width` pixels wide.
+    pub(crate) fn composite(&self, frame: &mut [u32], width: usize, rows: &Range<usize>) {
+        let height = frame.len() / width;
+        let images = self.images.iter().map(|(_, image)| image);
+        for overlay in images.chain(&self.overlays).chain(&self.preedit) {
+            let start = overlay.y.max(rows.start);
+            let end = overlay.rows().end.min(rows.end).min(height);
+            if overlay.x >= width {
+                continue;
+            }
+            let visible = overlay.pixels.width.min(width - overlay.x);
+            for y in start..end {
+                let source = (y - overlay.y) * overlay.pixels.width;
+                let target = y * width + overlay.x;
+                let source = &overlay.pixels.data[source..source + visible];
+                for (target, &pixel) in frame[target..target + visible].iter_mut().zip(source) {
+                    if pixel != TRANSPARENT {
+                        *target = pixel;
+                    }
+                }
+            }
+        }
+        for highlight in &self.selection {
+            let x = highlight.x.start.min(width)..highlight.x.end.min(width);
+            let start = highlight.y.start.max(rows.start);
+            let end = highlight.y.end.min(rows.end).min(height);
+            for y in start..end {
+                for pixel in &mut frame[y * width + x.start..y * width + x.end] {
+                    *pixel ^= 0x00ff_ffff;
+                }
+            }
+        }
+    }
+}
+
+/// Collects the overlays rendered on the TUI thread while it draws a frame.
+struct Frame {
+    size: Arc<Mutex<ScreenSize>>,
+    layer: Arc<Mutex<OverlayLayer>>,
+    pending_draw: Arc<AtomicBool>,
+    overlays: Vec<Overlay>,
+}
+
+impl Frame {
+    fn add(&mut self, area: Rect, paint: impl FnOnce(&mut Pixels)) {
+        let size = *self.size.lock().unwrap();
+        let (cell_width, cell_height) = (size.cell_width as usize, size.cell_height as usize);
+        let mut pixels = Pixels::new(
+            area.width as usize * cell_width,
+            area.height as usize * cell_height,
+        );
+        paint(&mut pixels);
+        self.overlays.push(Overlay {
+            x: area.x as usize * cell_width,
+            y: area.y as usize * cell_height,
+            pixels,
+        });
+    }
+}
+
+thread_local! {
+    // widgets only get a buffer to render into, so the overlays are collected on the side.
+    static FRAME: RefCell<Option<Frame>> = const { RefCell::new(None) };
+}
+
+/// Lets overlays rendered on the current thread be drawn over the window. Screens which can
+/// draw pixels call this when they create their backend, which happens on the TUI thread.
+pub(crate) fn capture_overlays(
+    size: Arc<Mutex<ScreenSize>>,
+    layer: Arc<Mutex<OverlayLayer>>,
+    pending_draw: Arc<AtomicBool>,
+) {
+    FRAME.set(Some(Frame {
+        size,
+        layer,
+        pending_draw,
+        overlays: Vec::new(),
+    }));
+}
+
+/// Hands the overlays rendered since the last call to the window. Backends call this when they
+/// are flushed, which ratatui does at the end of a frame, once the cells under the overlays have
+/// been handed over as well. Overlays committed any earlier could show up over the last frame.
+pub(crate) fn commit_overlays() {
+    FRAME.with_borrow_mut(|frame| {
+        if let Some(frame) = frame {
+            let overlays = std::mem::take(&mut frame.overlays);
+            if frame.layer.lock().unwrap().set(overlays) {
+                frame.pending_draw.store(true, Ordering::Relaxed);
+            }
+        }
+    });
+}
@@ -0,0 +1,662 @@
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+use std::str::Chars;
+use std::iter::Peekable;
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use winit::event_loop::EventLoop;
+
+use crate::options::Options;
+use crate::terminal::clipboard::Clipboard;
+use crate::terminal::font::{Fonts, restore_zoom};
+use crate::terminal::overlay::OverlayLayer;
+use crate::terminal::selection::ScreenText;
+use crate::terminal::sixel::SixelFilter;
+use crate::terminal::{
+    App, Display, ScreenSize, TerminalWriter, create_terminal, join_tui_thread, log,
+};
+use crate::theme::Themes;
+use crate::tui::screen::ScreenEvent;
+
+// how far the arrow keys seek, in seconds of the recording.
+const SEEK_STEP: f64 = 5.0;
+const MIN_SPEED: f64 = 1.0 / 16.0;
+const MAX_SPEED: f64 = 16.0;
+// the escape sequence which resets a terminal to its initial state.
+const RESET: &[u8] = b"\x1bc";
+
+/// Just enough of JSON to read asciicast files.
+enum Json {
+    /// `true`, `false` and `null`, which nothing in a recording needs the value of.
+    Literal,
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(text: &str) -> Option<Json> {
+        let mut chars = text.chars().peekable();
+        let value = Self::parse_value(&mut chars)?;
+        Self::skip_whitespace(&mut chars);
+        chars.next().is_none().then_some(value)
+    }
+
+    fn skip_whitespace(chars: &mut Peekable<Chars>) {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn parse_value(chars: &mut Peekable<Chars>) -> Option<Json> {
+        Self::skip_whitespace(chars);
+        match chars.peek()? {
+            '"' => Self::parse_string(chars).map(Json::String),
+            '[' => {
+                chars.next();
+                let mut items = Vec::new();
+                Self::skip_whitespace(chars);
+                if chars.next_if_eq(&']').is_some() {
+                    return Some(Json::Array(items));
+                }
+                loop {
+                    items.push(Self::parse_value(chars)?);
+                    Self::skip_whitespace(chars);
+                    match chars.next()? {
+                        ',' => continue,
+                        ']' => return Some(Json::Array(items)),
+                        _ => return None,
+                    }
+                }
+            }
+            '{' => {
+                chars.next();
+                let mut members = Vec::new();
+                Self::skip_whitespace(chars);
+                if chars.next_if_eq(&'}').is_some() {
+                    return Some(Json::Object(members));
+                }
+                loop {
+                    Self::skip_whitespace(chars);
+                    let key = Self::parse_string(chars)?;
+                    Self::skip_whitespace(chars);
+                    chars.next_if_eq(&':')?;
+                    members.push((key, Self::parse_value(chars)?));
+                    Self::skip_whitespace(chars);
+                    match chars.next()? {
+                        ',' => continue,
+                        '}' => return Some(Json::Object(members)),
+                        _ => return None,
+                    }
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || "+-.".contains(*c)) {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "null" | "true" | "false" => Some(Json::Literal),
+                    number => number.parse().ok().map(Json::Number),
+                }
+            }
+        }
+    }
+
+    fn parse_string(chars: &mut Peekable<Chars>) -> Option<String> {
+        chars.next_if_eq(&'"')?;
+        let mut text = String::new();
+        loop {
+            match chars.next()? {
+                '"' => return Some(text),
+                '\\' => match chars.next()? {
+                    'n' => text.push('\n'),
+                    'r' => text.push('\r'),
+                    't' => text.push('\t'),
+                    'b' => text.push('\u{8}'),
+                    'f' => text.push('\u{c}'),
+                    'u' => {
+                        let high = Self::parse_code_unit(chars)?;
+                        // characters outside the basic plane are written as surrogate pairs.
+                        let code = if (0xd800..0xdc00).contains(&high) {
+                            chars.next_if_eq(&'\\')?;
+                            chars.next_if_eq(&'u')?;
+                            let low = Self::parse_code_unit(chars)?;
+                            0x10000 + ((high - 0xd800) << 10) + (low.checked_sub(0xdc00)?)
+                        } else {
+                            high
+                        };
+                        text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    c => text.push(c),
+                },
+                c => text.push(c),
+            }
+        }
+    }
+
+    fn parse_code_unit(chars: &mut Peekable<Chars>) -> Option<u32> {
+        let hex = chars.by_ref().take(4).collect::<String>();
+        u32::from_str_radix(&hex, 16).ok()
+    }
+
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Json::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+/// Something which changed what was on screen in a recording.
+enum Change {
+    Output(String),
+    /// The terminal was resized to this many columns and rows.
+    Resize(u16, u16),
+}
+
+/// The parts of an asciicast v2 recording needed to play it back. Input events, and any others
+/// which don't change what was on screen, are skipped.
+struct Recording {
+    cols: u16,
+    rows: u16,
+    /// Output and resizes, and the time in seconds since the start that they happened.
+    changes: Vec<(f64, Change)>,
+}
+
+impl Recording {
+    fn parse(cast: &str) -> Result<Self, Box<dyn Error>> {
+        let mut lines = cast.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines.next().ok_or("the recording is empty")?;
+        let header = Json::parse(header).ok_or("the recording doesn't start with a header")?;
+        if header.get("version").and_then(Json::as_number) != Some(2.0) {
+            return Err("only asciicast version 2 recordings can be played".into());
+        }
+        let size = |key| header.get(key).and_then(Json::as_number).map(|n| n as u16);
+        let (Some(cols), Some(rows)) = (size("width"), size("height")) else {
+            return Err("the recording header has no width or height".into());
+        };
+
+        let mut changes = Vec::new();
+        for (number, line) in lines {
+            let invalid = || format!("line {}: invalid event", number + 1);
+            let Some(Json::Array(event)) = Json::parse(line) else {
+                return Err(invalid().into());
+            };
+            let [time, kind, data] = event.as_slice() else {
+                return Err(invalid().into());
+            };
+            let time = time.as_number().ok_or_else(invalid)?;
+            let data = data.as_str().ok_or_else(invalid)?;
+            match kind.as_str() {
+                Some("o") => changes.push((time, Change::Output(data.to_owned()))),
+                Some("r") => {
+                    let (cols, rows) = data.split_once('x').ok_or_else(invalid)?;
+                    let (Ok(cols), Ok(rows)) = (cols.parse(), rows.parse()) else {
+                        return Err(invalid().into());
+                    };
+                    changes.push((time, Change::Resize(cols, rows)));
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            cols,
+            rows,
+            changes,
+        })
+    }
+}
+
+/// Feeds a recording into the terminal, keeping track of where playback is.
+struct Player {
+    recording: Recording,
+    writer: TerminalWriter,
+    /// The index of the next change to play.
+    next: usize,
+    /// How far into the recording playback is, in seconds.
+    position: f64,
+    speed: f64,
+    playing: bool,
+    /// The size of the recorded terminal at this point of the recording.
+    size: (u16, u16),
+    /// The size the window was last asked to take.
+    requested_size: (u16, u16),
+    /// Where the window picks up the size it is asked to take.
+    grid_request: Arc<Mutex<Option<(u16, u16)>>>,
+}
+
+impl Player {
+    fn new(
+        recording: Recording,
+        writer: TerminalWriter,
+        grid_request: Arc<Mutex<Option<(u16, u16)>>>,
+    ) -> Self {
+        // the window starts out the recording's size.
+        let size = (recording.cols, recording.rows);
+        Self {
+            recording,
+            writer,
+            next: 0,
+            position: 0.0,
+            speed: 1.0,
+            playing: true,
+            size,
+            requested_size: size,
+            grid_request,
+        }
+    }
+
+    /// Plays everything up to the given time in the recording.
+    fn play_until(&mut self, time: f64) {
+        while let Some((at, change)) = self.recording.changes.get(self.next)
+            && *at <= time
+        {
+            match change {
+                Change::Output(data) => {
+                    let _ = self.writer.write_all(data.as_bytes());
+                }
+                Change::Resize(cols, rows) => self.size = (*cols, *rows),
+            }
+            self.next += 1;
+        }
+        let _ = self.writer.flush();
+        self.position = time;
+        // the window lays the terminal out again once it has resized, which starts playback
+        // over at the new size.
+        if self.size != self.requested_size {
+            self.requested_size = self.size;
+            *self.grid_request.lock().unwrap() = Some(self.size);
+        }
+    }
+
+    /// Jumps to a time in the recording. The terminal only moves forward, so going back means
+    /// starting again from the beginning.
+    fn seek(&mut self, time: f64) {
+        let time = time.clamp(0.0, self.end());
+        if time < self.position {
+            self.restart();
+        }
+        self.play_until(time);
+    }
+
+    /// Clears the terminal and goes back to the start of the recording.
+    fn restart(&mut self) {
+        let _ = self.writer.write_all(RESET);
+        self.next = 0;
+        self.position = 0.0;
+        self.size = (self.recording.cols, self.recording.rows);
+    }
+
+    /// Plays the next change, however far away it is.
+    fn step(&mut self) {
+        if let Some((at, _)) = self.recording.changes.get(self.next) {
+            let at = *at;
+            self.play_until(at);
+        }
+    }
+
+    fn end(&self) -> f64 {
+        self.recording.changes.last().map_or(0.0, |(at, _)| *at)
+    }
+
+    /// How long to wait, in real time, before the next change is due.
+    fn next_due(&self) -> Option<Duration> {
+        let (at, _) = self.recording.changes.get(self.next)?;
+        let wait = ((at - self.position) / self.speed).max(0.0);
+        self.playing.then(|| Duration::from_secs_f64(wait))
+    }
+
+    /// Returns false once the player should stop.
+    fn handle(&mut self, event: ScreenEvent) -> bool {
+        let key = match event {
+            ScreenEvent::CloseRequested => return false,
+            // the output is laid out again in the window's new size.
+            ScreenEvent::Input(Event::Resize(..)) => {
+                let position = self.position;
+                self.restart();
+                self.play_until(position);
+                return true;
+            }
+            ScreenEvent::Input(Event::Key(key)) if key.kind != KeyEventKind::Release => key,
+            ScreenEvent::Input(_) => return true,
+        };
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char(' ') => self.playing = !self.playing,
+            KeyCode::Char('.') => {
+                self.playing = false;
+                self.step();
+            }
+            KeyCode::Char('+' | '=') => self.speed = (self.speed * 2.0).min(MAX_SPEED),
+            KeyCode::Char('-') => self.speed = (self.speed / 2.0).max(MIN_SPEED),
+            KeyCode::Right => self.seek(self.position + SEEK_STEP),
+            KeyCode::Left => self.seek(self.position - SEEK_STEP),
+            KeyCode::Home => self.seek(0.0),
+            KeyCode::End => self.seek(self.end()),
+            _ => {}
+        }
+        true
+    }
+
+    fn run(&mut self, input_rx: Receiver<ScreenEvent>) {
+        loop {
+            // while paused, or once the recording is over, only the controls can move things on.
+            // An update the recording doesn't end is shown once it times out.
+            let started = Instant::now();
+            let wait = self.next_due().into_iter().chain(self.writer.held_timeout()).min();
+            let event = match wait {
+                Some(wait) => input_rx.recv_timeout(wait),
+                None => input_rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            if self.playing {
+                let elapsed = started.elapsed().as_secs_f64() * self.speed;
+                self.play_until(self.position + elapsed);
+            } else {
+                let _ = self.writer.flush();
+            }
+            match event {
+                Ok(event) => {
+                    if !self.handle(event) {
+                        return;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+    }
+}
+
+/// Plays an asciicast v2 recording in the window, with the original timing, through the same
+/// terminal and font used for live sessions. This must be called from the main thread.
+///
+/// | Key           | Action                                   |
+/// |---------------|------------------------------------------|
+/// | space         | pause or resume                          |
+/// | `.`           | pause and step to the next output        |
+/// | `+` and `-`   | double or halve the speed                |
+/// | left, right   | seek back or forward five seconds        |
+/// | home, end     | seek to the start or the end             |
+/// | `q`, escape   | quit                                     |
+pub fn run_playback(path: &Path, options: Options) -> Result<(), Box<dyn Error>> {
+    let cast = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read recording {}: {}", path.display(), e))?;
+    let recording = Recording::parse(&cast)
+        .map_err(|e| format!("could not read recording {}: {}", path.display(), e))?;
+
+    let display = Display::default();
+    let (input_tx, input_rx) = channel::<ScreenEvent>();
+
+    let fonts = Fonts::load(&options.font)?.with_zoom(restore_zoom());
+    let themes = Themes::new(&options)?;
+    let mut terminal = create_terminal(display.clone(), &fonts);
+    terminal.set_custom_color_scheme(&themes.current().palette());
+    terminal.set_auto_flush(false);
+    terminal.set_logger(log);
+    let clipboard = Clipboard::new();
+    terminal.set_clipboard(Box::new(clipboard.clone()));
+
+    let (cell_width, cell_height) = fonts.cell_size();
+    let window_size = (
+        recording.cols as usize * cell_width,
+        recording.rows as usize * cell_height,
+    );
+    // a recording's queries were answered when it was recorded, so the size isn't reported.
+    let size = ScreenSize::new(recording.cols as usize, recording.rows as usize, fonts.cell_size());
+    let size = Arc::new(Mutex::new(size));
+    let overlays = Arc::new(Mutex::new(OverlayLayer::default()));
+    let text = Arc::new(Mutex::new(ScreenText::default()));
+    let grid_request = Arc::new(Mutex::new(None));
+
+    let terminal = Arc::new(Mutex::new(terminal));
+    let pending_draw = Arc::new(AtomicBool::new(false));
+
+    let event_loop = EventLoop::new()?;
+    let event_loop_proxy = event_loop.create_proxy();
+
+    let player_thread = std::thread::spawn({
+        let sixels = SixelFilter::new(size.clone(), overlays.clone());
+        let writer = TerminalWriter::new(
+            terminal.clone(),
+            pending_draw.clone(),
+            None,
+            Some(sixels),
+            None,
+        );
+        let grid_request = grid_request.clone();
+        move || {
+            let mut player = Player::new(recording, writer, grid_request);
+            player.run(input_rx);
+            let _ = event_loop_proxy.send_event(());
+            Ok(())
+        }
+    });
+
+    let mut handler = App::new(
+        options.title.clone(),
+        display,
+        terminal,
+        None,
+        pending_draw,
+        input_tx,
+        size,
+        fonts,
+        themes,
+        clipboard,
+    );
+    // the window starts out the size of the recorded terminal.
+    handler.initial_size = window_size;
+    handler.overlays = overlays;
+    handler.text = Some(text);
+    handler.follow_terminal = true;
+    handler.grid_request = grid_request;
+
+    let result = event_loop.run_app(&mut handler);
+    drop(handler);
+
+    join_tui_thread(player_thread)?;
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::crossterm::event::{KeyEvent, KeyModifiers};
+
+    use super::*;
+    use crate::options::Options;
+
+    const CAST: &str = r#"{"version": 2, "width": 10, "height": 4, "env": {"TERM": "xterm"}}
+[0.5, "o", "a"]
+[1.0, "i", "x"]
+[1.5, "o", "b"]
+[2.0, "r", "20x5"]
+[2.0, "m", ""]
+[3.0, "o", "c"]
+"#;
+
+    fn player(cast: &str) -> Player {
+        let fonts = Fonts::load(&Options::default().font).unwrap();
+        let terminal = create_terminal(Display::new(160, 64), &fonts);
+        let terminal = Arc::new(Mutex::new(terminal));
+        let pending_draw = Arc::new(AtomicBool::new(false));
+        let writer = TerminalWriter::new(terminal, pending_draw, None, None, None);
+        Player::new(Recording::parse(cast).unwrap(), writer, Arc::default())
+    }
+
+    /// The first row of the terminal the player writes to.
+    fn shown(player: &Player) -> String {
+        let terminal = player.writer.terminal.lock().unwrap();
+        let row = (0..terminal.columns()).filter_map(|column| terminal.cell_content(0, column));
+        row.collect::<String>().trim_end().to_string()
+    }
+
+    fn press(player: &mut Player, code: KeyCode) -> bool {
+        player.handle(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)).into())
+    }
+
+    #[test]
+    fn json_values_are_parsed() {
+        let Some(Json::Array(items)) = Json::parse(r#" [1.5, -2e3, "a\"\\\/\n", true, null] "#)
+        else {
+            panic!("not an array");
+        };
+        assert_eq!(items[0].as_number(), Some(1.5));
+        assert_eq!(items[1].as_number(), Some(-2000.0));
+        assert_eq!(items[2].as_str(), Some("a\"\\/\n"));
+        assert!(matches!(items[3..], [Json::Literal, Json::Literal]));
+
+        let object = Json::parse(r#"{"a": {"b": []}, "c": "d"}"#).unwrap();
+        assert!(matches!(
+            object.get("a").and_then(|a| a.get("b")),
+            Some(Json::Array(_))
+        ));
+        assert_eq!(object.get("c").and_then(Json::as_str), Some("d"));
+        assert!(object.get("e").is_none());
+    }
+
+    #[test]
+    fn json_strings_join_surrogate_pairs() {
+        let crab = Json::parse(r#""\u00e9\ud83e\udd80""#).unwrap();
+        assert_eq!(crab.as_str(), Some("é🦀"));
+        // a high surrogate has to be followed by a low one.
+        assert!(Json::parse(r#""\ud83e""#).is_none());
+        assert!(Json::parse(r#""\ud83eA""#).is_none());
+    }
+
+    #[test]
+    fn invalid_json_is_rejected() {
+        for text in [
+            "",
+            "[1, 2",
+            "[1 2]",
+            r#"{"a" 1}"#,
+            r#""open"#,
+            "[1] 2",
+            "nope",
+        ] {
+            assert!(Json::parse(text).is_none(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn recordings_keep_output_and_resizes() {
+        let recording = Recording::parse(CAST).unwrap();
+        assert_eq!((recording.cols, recording.rows), (10, 4));
+        let changes: Vec<_> = recording
+            .changes
+            .iter()
+            .map(|(at, change)| match change {
+                Change::Output(data) => format!("{} {}", at, data),
+                Change::Resize(cols, rows) => format!("{} {}x{}", at, cols, rows),
+            })
+            .collect();
+        assert_eq!(changes, ["0.5 a", "1.5 b", "2 20x5", "3 c"]);
+    }
+
+    #[test]
+    fn invalid_recordings_are_reported() {
+        let errors = [
+            ("", "the recording is empty"),
+            ("[0.5, \"o\"", "the recording doesn't start with a header"),
+            (
+                r#"{"version": 1, "width": 10, "height": 4}"#,
+                "only asciicast version 2",
+            ),
+            (
+                r#"{"version": 2, "width": 10}"#,
+                "the recording header has no width",
+            ),
+            (
+                "{\"version\": 2, \"width\": 1, \"height\": 1}\n\n[1, \"o\"]",
+                "line 3: invalid",
+            ),
+            (
+                "{\"version\": 2, \"width\": 1, \"height\": 1}\n[1, \"r\", \"20\"]",
+                "line 2: invalid",
+            ),
+        ];
+        for (cast, error) in errors {
+            let Err(e) = Recording::parse(cast) else {
+                panic!("{:?} was parsed", cast);
+            };
+            assert!(e.to_string().starts_with(error), "{:?}: {}", cast, e);
+        }
+    }
+
+    #[test]
+    fn seeking_plays_up_to_the_time() {
+        let mut player = player(CAST);
+        player.seek(1.5);
+        assert_eq!((player.next, shown(&player)), (2, "ab".to_string()));
+        // going back starts over.
+        player.seek(1.0);
+        assert_eq!((player.next, shown(&player)), (1, "a".to_string()));
+        player.seek(10.0);
+        assert_eq!((player.position, shown(&player)), (3.0, "abc".to_string()));
+        assert!(press(&mut player, KeyCode::Home));
+        assert_eq!((player.position, shown(&player)), (0.0, String::new()));
+    }
+
+    #[test]
+    fn stepping_pauses_and_plays_the_next_change() {
+        let mut player = player(CAST);
+        assert!(press(&mut player, KeyCode::Char('.')));
+        assert!(!player.playing);
+        assert_eq!((player.position, shown(&player)), (0.5, "a".to_string()));
+        press(&mut player, KeyCode::Char('.'));
+        assert_eq!((player.position, shown(&player)), (1.5, "ab".to_string()));
+        assert!(press(&mut player, KeyCode::Char(' ')));
+        assert!(player.playing);
+        assert!(!press(&mut player, KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn speed_is_kept_within_limits() {
+        let mut player = player(CAST);
+        press(&mut player, KeyCode::Char('+'));
+        assert_eq!(player.speed, 2.0);
+        assert_eq!(player.next_due(), Some(Duration::from_secs_f64(0.25)));
+        for _ in 0..10 {
+            press(&mut player, KeyCode::Char('+'));
+        }
+        assert_eq!(player.speed, MAX_SPEED);
+        for _ in 0..20 {
+            press(&mut player, KeyCode::Char('-'));
+        }
+        assert_eq!(player.speed, MIN_SPEED);
+    }
+
+    #[test]
+    fn the_window_is_asked_to_follow_resizes() {
+        let mut player = player(CAST);
+        let request = player.grid_request.clone();
+        player.seek(1.5);
+        assert_eq!(*request.lock().unwrap(), None);
+        player.seek(2.0);
+        assert_eq!(request.lock().unwrap().take(), Some((20, 5)));
+        // playing the same part again after the window has resized asks for nothing.
+        player.handle(Event::Resize(20, 5).into());
+        assert_eq!(request.lock().unwrap().take(), None);
+        player.seek(1.0);
+        assert_eq!(request.lock().unwrap().take(), Some((10, 4)));
+    }
+}
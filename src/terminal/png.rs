@@ -0,0 +1,90 @@
+use std::io::{self, Write};
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+// the largest amount of data a single stored deflate block can hold.
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+fn crc32(data: &[&[u8]]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data.iter().flat_map(|part| part.iter()) {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    out.write_all(&crc32(&[kind, data]).to_be_bytes())
+}
+
+/// Wraps data in a zlib stream made of uncompressed deflate blocks. Snapshots are small enough
+/// that compression isn't worth an extra dependency.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut result = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    result.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        result.extend_from_slice(&[0x01, 0x00, 0x00, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        result.push(last as u8);
+        result.extend_from_slice(&len.to_le_bytes());
+        result.extend_from_slice(&(!len).to_le_bytes());
+        result.extend_from_slice(chunk);
+    }
+
+    result.extend_from_slice(&adler32(data).to_be_bytes());
+    result
+}
+
+/// Writes 0x00RRGGBB pixels, as stored in the display, as an 8-bit RGB PNG image.
+pub(crate) fn write_png<W: Write>(
+    mut out: W,
+    width: usize,
+    height: usize,
+    pixels: impl Iterator<Item = u32>,
+) -> io::Result<()> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // bit depth 8, color type RGB, default compression, filter and interlace methods.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+    let pixels = pixels.collect::<Vec<_>>();
+    let mut scanlines = Vec::with_capacity(height * (width * 3 + 1));
+    for row in pixels.chunks(width.max(1)).take(height) {
+        // every scanline starts with its filter type, which is always none.
+        scanlines.extend(std::iter::once(0).chain(row.iter().flat_map(|pixel| {
+            let [_, r, g, b] = pixel.to_be_bytes();
+            [r, g, b]
+        })));
+    }
+
+    out.write_all(SIGNATURE)?;
+    write_chunk(&mut out, b"IHDR", &header)?;
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&scanlines))?;
+    write_chunk(&mut out, b"IEND", &[])?;
+    out.flush()
+}
@@ -0,0 +1,43 @@
+use os_terminal::font::{ContentInfo, FontManager};
+use ratatui::text::Span;
+
+use crate::terminal::font::{FallbackFont, Fonts};
+use crate::terminal::overlay::Pixels;
+use crate::terminal::{blend, draw_raster};
+use crate::theme::Theme;
+
+/// Draws text an input method is composing, underlined, the way the terminal would draw it
+/// in cells of the default colors. Characters which don't take up a cell of their own, such as
+/// combining marks, are left out.
+pub(super) fn draw_preedit(text: &str, fonts: &Fonts, theme: &Theme) -> Pixels {
+    let mut font = FallbackFont::new(fonts);
+    let (cell_width, cell_height) = font.size();
+    let chars = text
+        .chars()
+        .map(|c| (c, Span::raw(c.to_string()).width()))
+        .filter(|(_, width)| *width > 0)
+        .collect::<Vec<_>>();
+    let cells = chars.iter().map(|(_, width)| width).sum::<usize>();
+
+    let mut pixels = Pixels::new(cells * cell_width, cell_height);
+    pixels.fill(theme.background);
+    let mut x_start = 0;
+    for (content, width) in chars {
+        let info = ContentInfo {
+            content,
+            bold: false,
+            italic: false,
+            wide: width > 1,
+        };
+        draw_raster(font.rasterize(info), |x, y, intensity| {
+            let color = blend(theme.foreground, theme.background, intensity);
+            pixels.set(x_start + x, y, color);
+        });
+        x_start += width * cell_width;
+    }
+
+    for x in 0..pixels.width() {
+        pixels.set(x, cell_height - 1, theme.foreground);
+    }
+    pixels
+}
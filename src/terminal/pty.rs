@@ -0,0 +1,329 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use os_terminal::MouseInput;
+use ratatui::crossterm::event::{Event, MouseEventKind};
+use winit::event_loop::EventLoop;
+
+use crate::options::Options;
+use crate::terminal::clipboard::Clipboard;
+use crate::terminal::font::{Fonts, restore_zoom};
+use crate::terminal::input::encode_input;
+use crate::terminal::modes::{ModeScanner, MouseTracking};
+use crate::terminal::osc52::ClipboardFilter;
+use crate::terminal::report::SizeReporter;
+use crate::terminal::selection::ScreenText;
+use crate::terminal::{
+    App, Display, ReplyWriter, ScreenSize, TerminalWriter, create_recorder, create_terminal,
+    join_tui_thread, log,
+};
+use crate::theme::Themes;
+use crate::tui::screen::ScreenEvent;
+
+// how much of the program's output is read at once.
+const READ_BUFFER_SIZE: usize = 4096;
+
+fn window_size(size: ScreenSize) -> libc::winsize {
+    let pixels = size.pixels();
+    libc::winsize {
+        ws_row: size.rows,
+        ws_col: size.cols,
+        ws_xpixel: pixels.width,
+        ws_ypixel: pixels.height,
+    }
+}
+
+fn check(result: libc::c_int) -> io::Result<libc::c_int> {
+    if result == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result)
+    }
+}
+
+/// Waits up to the timeout for something to read from a descriptor. Returns false if nothing
+/// arrived in time.
+fn wait_readable(fd: RawFd, timeout: Duration) -> bool {
+    let mut poll = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+    // SAFETY: the descriptor set is valid for the duration of the call.
+    unsafe { libc::poll(&mut poll, 1, timeout) != 0 }
+}
+
+/// Keeps a descriptor from leaking into the program, which only gets the ones it is given.
+fn set_close_on_exec(fd: RawFd) -> io::Result<()> {
+    // SAFETY: fcntl doesn't touch memory, a bad descriptor is reported as an error.
+    check(unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) })?;
+    Ok(())
+}
+
+/// The controlling side of a pseudo-terminal, with a program running on the other side.
+struct Pty {
+    master: File,
+    child: Child,
+}
+
+impl Pty {
+    fn spawn(mut command: Command, size: ScreenSize) -> io::Result<Self> {
+        let (mut master, mut slave) = (-1, -1);
+        let size = window_size(size);
+        // SAFETY: the pointers are valid for the duration of the call, the name and terminal
+        // settings are optional.
+        check(unsafe {
+            libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size)
+        })?;
+        // SAFETY: openpty succeeded, so both descriptors are open and nothing else owns them.
+        let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+        set_close_on_exec(master.as_raw_fd())?;
+        set_close_on_exec(slave.as_raw_fd())?;
+
+        command
+            .stdin(slave.try_clone()?)
+            .stdout(slave.try_clone()?)
+            .stderr(slave)
+            .env("TERM", "xterm-256color");
+        // SAFETY: only async-signal-safe functions are called between fork and exec.
+        unsafe {
+            command.pre_exec(|| {
+                // the program leads a new session, with the pty as its controlling terminal.
+                check(libc::setsid())?;
+                check(libc::ioctl(0, libc::TIOCSCTTY, 0))?;
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+        // the command holds on to the slave side until it's dropped, which would keep the
+        // master from seeing the end of the output when the program exits.
+        drop(command);
+
+        Ok(Self {
+            master: File::from(master),
+            child,
+        })
+    }
+
+    fn try_clone_master(&self) -> io::Result<File> {
+        self.master.try_clone()
+    }
+
+    /// Tells the program its window changed size, which it learns through `SIGWINCH`.
+    fn resize(&self, size: ScreenSize) -> io::Result<()> {
+        let size = window_size(size);
+        // SAFETY: the size is valid for the duration of the call.
+        check(unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ, &size) })?;
+        Ok(())
+    }
+
+    /// Hangs up on the program, as a terminal does when its window is closed.
+    fn hang_up(&self) {
+        // the program leads its own process group, so everything it started is told as well.
+        // SAFETY: kill doesn't touch memory. If the program has already exited there's nobody
+        // to tell, which isn't an error.
+        unsafe {
+            libc::kill(-(self.child.id() as libc::pid_t), libc::SIGHUP);
+        }
+    }
+}
+
+/// Runs a program in the window, connected through a pseudo-terminal, until it exits or the
+/// window is closed. This is how programs which aren't ratatui applications in this process,
+/// such as a shell, are hosted. This must be called from the main thread.
+pub fn run_command(command: Command, options: Options) -> Result<(), Box<dyn Error>> {
+    let display = Display::default();
+
+    let fonts = Fonts::load(&options.font)?.with_zoom(restore_zoom());
+    let themes = Themes::new(&options)?;
+    let mut terminal = create_terminal(display.clone(), &fonts);
+    terminal.set_custom_color_scheme(&themes.current().palette());
+    terminal.set_auto_flush(false);
+    terminal.set_logger(log);
+    let clipboard = Clipboard::new();
+    terminal.set_clipboard(Box::new(clipboard.clone()));
+
+    let size = ScreenSize::new(terminal.columns(), terminal.rows(), fonts.cell_size());
+    let pty = Pty::spawn(command, size)?;
+    let size = Arc::new(Mutex::new(size));
+
+    // replies to the program's queries, and keys os-terminal generates itself, go straight
+    // back to it. If it has exited there's nobody left to write to.
+    let replies = pty.try_clone_master()?;
+    terminal.set_pty_writer(Box::new(move |data| {
+        let _ = (&replies).write_all(data.as_bytes());
+    }));
+
+    let recorder = create_recorder(&options, terminal.columns(), terminal.rows())?;
+    let text = Arc::new(Mutex::new(ScreenText::default()));
+
+    let terminal = Arc::new(Mutex::new(terminal));
+    let pending_draw = Arc::new(AtomicBool::new(false));
+    let scanner = Arc::new(Mutex::new(ModeScanner::new()));
+
+    let event_loop = EventLoop::new()?;
+    let event_loop_proxy = event_loop.create_proxy();
+    let (input_tx, input_rx) = channel::<ScreenEvent>();
+
+    std::thread::spawn({
+        let mut output = pty.try_clone_master()?;
+        // the program only hears back from the clipboard if it's allowed to read it.
+        let replies = pty.try_clone_master()?;
+        let reply: ReplyWriter = Box::new(move |data| {
+            let _ = (&replies).write_all(data);
+        });
+        let reply = options.clipboard_read.then_some(reply);
+        let clipboard = ClipboardFilter::new(clipboard.clone(), reply);
+        // sixel images are left to os-terminal, which drops them: programs scroll the screen,
+        // which images laid over the display couldn't follow.
+        let mut writer = TerminalWriter::new(
+            terminal.clone(),
+            pending_draw.clone(),
+            recorder.clone(),
+            None,
+            Some(clipboard),
+        );
+        // os-terminal doesn't know its size in pixels, so those queries are answered here.
+        let replies = pty.try_clone_master()?;
+        let mut reporter = SizeReporter::new(
+            size.clone(),
+            Box::new(move |data| {
+                let _ = (&replies).write_all(data);
+            }),
+        );
+        let scanner = scanner.clone();
+        move || {
+            let mut buf = [0; READ_BUFFER_SIZE];
+            loop {
+                // an update the program doesn't end is shown once it times out, whether or not
+                // there's more output by then.
+                if let Some(timeout) = writer.held_timeout()
+                    && !wait_readable(output.as_raw_fd(), timeout)
+                {
+                    if let Err(e) = writer.flush() {
+                        log(format_args!("could not record the output: {}", e));
+                    }
+                    continue;
+                }
+                // reading fails once the program, and anything else using the pty, has exited.
+                let len = match output.read(&mut buf) {
+                    Ok(len) if len > 0 => len,
+                    _ => break,
+                };
+                scanner.lock().unwrap().scan(&buf[..len]);
+                reporter.scan(&buf[..len]);
+                // the writer only fails when the output can't be recorded.
+                if let Err(e) = writer.write_all(&buf[..len]).and_then(|_| writer.flush()) {
+                    log(format_args!("could not record the output: {}", e));
+                }
+            }
+            let _ = event_loop_proxy.send_event(());
+        }
+    });
+
+    let input_thread = std::thread::spawn({
+        let terminal = terminal.clone();
+        let scanner = scanner.clone();
+        let pending_draw = pending_draw.clone();
+        let mut input = pty.try_clone_master()?;
+        let size = size.clone();
+        move || {
+            // how far back into the history the wheel has scrolled, in wheel steps.
+            let mut history_offset = 0isize;
+            for event in input_rx {
+                let modes = scanner.lock().unwrap().modes();
+                match event {
+                    ScreenEvent::Input(Event::Resize(cols, rows)) => {
+                        history_offset = 0;
+                        // the window has already laid out the new size, cell size included.
+                        if let Err(e) = pty.resize(*size.lock().unwrap()) {
+                            log(format_args!("could not resize the program's terminal: {}", e));
+                        }
+                        if let Some(recorder) = &recorder
+                            && let Err(e) = recorder.resize(cols, rows)
+                        {
+                            log(format_args!("could not record the resize: {}", e));
+                        }
+                    }
+                    // os-terminal scrolls its history, or sends cursor keys to programs on the
+                    // alternate screen, when the program doesn't want the wheel itself.
+                    ScreenEvent::Input(Event::Mouse(mouse))
+                        if modes.mouse == MouseTracking::Off
+                            && matches!(
+                                mouse.kind,
+                                MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+                            ) =>
+                    {
+                        let lines = if mouse.kind == MouseEventKind::ScrollUp { 1 } else { -1 };
+                        if !modes.alternate_screen {
+                            history_offset = (history_offset + lines).max(0);
+                        }
+                        let mut terminal = terminal.lock().unwrap();
+                        terminal.handle_mouse(MouseInput::Scroll(lines));
+                        pending_draw.store(true, Ordering::Relaxed);
+                    }
+                    ScreenEvent::Input(event) => {
+                        let Some(bytes) = encode_input(&event, modes) else {
+                            continue;
+                        };
+                        // typing returns to the bottom of the history, like it would in
+                        // os-terminal's own keyboard handling.
+                        if history_offset > 0 && !modes.alternate_screen {
+                            let mut terminal = terminal.lock().unwrap();
+                            terminal.handle_mouse(MouseInput::Scroll(-history_offset));
+                            pending_draw.store(true, Ordering::Relaxed);
+                        }
+                        history_offset = 0;
+                        // a program which has exited can't be written to, the output thread
+                        // closes the window when that happens.
+                        let _ = input.write_all(&bytes);
+                        if let Some(recorder) = &recorder
+                            && let Err(e) = recorder.input_bytes(&bytes)
+                        {
+                            log(format_args!("could not record the input: {}", e));
+                        }
+                    }
+                    ScreenEvent::CloseRequested => pty.hang_up(),
+                }
+            }
+
+            // the window has closed, make sure the program goes with it.
+            let mut pty = pty;
+            pty.hang_up();
+            pty.child.wait().map_err(|e| e.to_string())?;
+            Ok(())
+        }
+    });
+
+    let mut handler = App::new(
+        options.title.clone(),
+        display,
+        terminal,
+        None,
+        pending_draw,
+        input_tx,
+        size,
+        fonts,
+        themes,
+        clipboard,
+    );
+    handler.text = Some(text);
+    handler.follow_terminal = true;
+    handler.modes = Some(scanner);
+
+    let result = event_loop.run_app(&mut handler);
+    drop(handler);
+
+    join_tui_thread(input_thread)?;
+    Ok(result?)
+}
+
@@ -0,0 +1,173 @@
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use ratatui::crossterm::event::Event;
+
+use crate::terminal::input::encode_input;
+use crate::terminal::modes::ModeScanner;
+
+/// Quotes text as a JSON string.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+struct RecorderState {
+    file: BufWriter<File>,
+    // output which ended part way through a character, held until the rest arrives.
+    partial: Vec<u8>,
+    // input is recorded the way the application asked for it, mouse reports in particular.
+    modes: ModeScanner,
+}
+
+impl RecorderState {
+    fn event(&mut self, start: Instant, kind: &str, data: &str) -> io::Result<()> {
+        let time = start.elapsed().as_secs_f64();
+        writeln!(self.file, "[{:.6}, \"{}\", {}]", time, kind, json_string(data))?;
+        // events are flushed as they happen, so a crash still leaves a usable recording.
+        self.file.flush()
+    }
+}
+
+/// Writes everything the terminal shows, and the input it receives, to an
+/// [asciicast v2](https://docs.asciinema.org/manual/asciicast/v2/) file.
+pub(crate) struct Recorder {
+    start: Instant,
+    state: Mutex<RecorderState>,
+}
+
+impl Recorder {
+    pub(crate) fn create(path: &Path, cols: u16, rows: u16, title: &str) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        writeln!(
+            file,
+            "{{\"version\": 2, \"width\": {}, \"height\": {}, \"timestamp\": {}, \"title\": {}, \"env\": {{\"TERM\": \"xterm-256color\"}}}}",
+            cols,
+            rows,
+            timestamp,
+            json_string(title),
+        )?;
+        file.flush()?;
+
+        Ok(Self {
+            start: Instant::now(),
+            state: Mutex::new(RecorderState {
+                file,
+                partial: Vec::new(),
+                modes: ModeScanner::new(),
+            }),
+        })
+    }
+
+    /// Records output written to the terminal.
+    pub(crate) fn output(&self, data: &[u8]) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.modes.scan(data);
+
+        let mut output = std::mem::take(&mut state.partial);
+        output.extend_from_slice(data);
+        // only a character cut off at the end is held back, anything else invalid is replaced.
+        if let Err(e) = std::str::from_utf8(&output)
+            && e.error_len().is_none()
+        {
+            state.partial = output.split_off(e.valid_up_to());
+        }
+        if output.is_empty() {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&output);
+        state.event(self.start, "o", &text)
+    }
+
+    /// Records input as the bytes a terminal would have sent for it. Resizes are recorded as
+    /// resize events, and events the application didn't ask for are left out.
+    pub(crate) fn input(&self, event: &Event) -> io::Result<()> {
+        if let Event::Resize(cols, rows) = event {
+            return self.resize(*cols, *rows);
+        }
+        let modes = self.state.lock().unwrap().modes.modes();
+        match encode_input(event, modes) {
+            Some(bytes) => self.input_bytes(&bytes),
+            None => Ok(()),
+        }
+    }
+
+    /// Records input which is already encoded.
+    pub(crate) fn input_bytes(&self, data: &[u8]) -> io::Result<()> {
+        let text = String::from_utf8_lossy(data);
+        self.state.lock().unwrap().event(self.start, "i", &text)
+    }
+
+    pub(crate) fn resize(&self, cols: u16, rows: u16) -> io::Result<()> {
+        let size = format!("{}x{}", cols, rows);
+        self.state.lock().unwrap().event(self.start, "r", &size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_are_escaped() {
+        assert_eq!(json_string("say \"hi\"\\"), r#""say \"hi\"\\""#);
+        assert_eq!(json_string("a\r\n\tb"), r#""a\r\n\tb""#);
+        assert_eq!(
+            json_string("\x1b[0m\x7f\u{85}"),
+            r#""\u001b[0m\u007f\u0085""#
+        );
+        // JSON text is UTF-8, so characters outside of the BMP don't need surrogates.
+        assert_eq!(json_string("日本 🦀"), "\"日本 🦀\"");
+    }
+
+    #[test]
+    fn recordings_hold_characters_split_across_writes() {
+        let path = std::env::temp_dir().join(format!("record-{}.cast", std::process::id()));
+        let recorder = Recorder::create(&path, 10, 4, "a \"title\"").unwrap();
+        recorder.output(b"\xe6\x97").unwrap();
+        recorder.output(b"\xa5a\xe6").unwrap();
+        recorder.output(b"\x97\xa5\xff").unwrap();
+        recorder.resize(20, 5).unwrap();
+        recorder.input_bytes(b"\x1b[A").unwrap();
+        let cast = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<_> = cast.lines().collect();
+        assert_eq!(lines.len(), 5, "{}", cast);
+        assert!(lines[0].starts_with(r#"{"version": 2, "width": 10, "height": 4, "timestamp": "#));
+        assert!(
+            lines[0].ends_with(r#""title": "a \"title\"", "env": {"TERM": "xterm-256color"}}"#)
+        );
+        let events = [
+            r#""o", "日a"]"#,
+            "\"o\", \"日\u{fffd}\"]",
+            r#""r", "20x5"]"#,
+            r#""i", "\u001b[A"]"#,
+        ];
+        for (line, event) in lines[1..].iter().zip(events) {
+            assert!(line.starts_with('[') && line.ends_with(event), "{}", line);
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::{Arc, Mutex};
+
+use crate::terminal::{ReplyWriter, ScreenSize};
+
+/// A query about the size of the screen in pixels, which os-terminal doesn't answer itself.
+enum SizeQuery {
+    /// `CSI 14 t`, the size of the text area.
+    TextArea,
+    /// `CSI 16 t`, the size of a cell.
+    Cell,
+}
+
+enum State {
+    Ground,
+    Escape,
+    /// Inside `ESC [`, collecting the parameters.
+    Csi(Vec<u16>, Option<u16>),
+}
+
+/// Answers the pixel size queries in a stream of output, with the size the window last
+/// reported. Queries may be split across calls.
+pub(crate) struct SizeReporter {
+    size: Arc<Mutex<ScreenSize>>,
+    reply: ReplyWriter,
+    state: State,
+}
+
+impl SizeReporter {
+    pub(crate) fn new(size: Arc<Mutex<ScreenSize>>, reply: ReplyWriter) -> Self {
+        Self {
+            size,
+            reply,
+            state: State::Ground,
+        }
+    }
+
+    pub(crate) fn scan(&mut self, output: &[u8]) {
+        for byte in output {
+            self.state = match (std::mem::replace(&mut self.state, State::Ground), byte) {
+                (_, 0x1b) => State::Escape,
+                (State::Escape, b'[') => State::Csi(Vec::new(), None),
+                (State::Csi(params, current), b'0'..=b'9') => {
+                    let digit = (byte - b'0') as u16;
+                    let current = current.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+                    State::Csi(params, Some(current))
+                }
+                (State::Csi(mut params, current), b';') => {
+                    params.push(current.unwrap_or(0));
+                    State::Csi(params, None)
+                }
+                (State::Csi(mut params, current), b't') => {
+                    params.extend(current);
+                    match params.first() {
+                        Some(14) => self.report(SizeQuery::TextArea),
+                        Some(16) => self.report(SizeQuery::Cell),
+                        _ => {}
+                    }
+                    State::Ground
+                }
+                _ => State::Ground,
+            };
+        }
+    }
+
+    fn report(&self, query: SizeQuery) {
+        let size = *self.size.lock().unwrap();
+        let reply = match query {
+            SizeQuery::TextArea => {
+                let pixels = size.pixels();
+                format!("\x1b[4;{};{}t", pixels.height, pixels.width)
+            }
+            SizeQuery::Cell => format!("\x1b[6;{};{}t", size.cell_height, size.cell_width),
+        };
+        (self.reply)(reply.as_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::channel;
+
+    use super::*;
+
+    fn reporter() -> (SizeReporter, std::sync::mpsc::Receiver<Vec<u8>>) {
+        let size = ScreenSize {
+            cols: 80,
+            rows: 24,
+            cell_width: 9,
+            cell_height: 20,
+        };
+        let (tx, rx) = channel();
+        let reply: ReplyWriter = Box::new(move |data| tx.send(data.to_vec()).unwrap());
+        (SizeReporter::new(Arc::new(Mutex::new(size)), reply), rx)
+    }
+
+    #[test]
+    fn answers_size_queries() {
+        let (mut reporter, replies) = reporter();
+        reporter.scan(b"text\x1b[14t\x1b[16tmore");
+        assert_eq!(replies.try_recv().unwrap(), b"\x1b[4;480;720t");
+        assert_eq!(replies.try_recv().unwrap(), b"\x1b[6;20;9t");
+        assert!(replies.try_recv().is_err());
+    }
+
+    #[test]
+    fn answers_queries_split_across_output() {
+        let (mut reporter, replies) = reporter();
+        for chunk in [&b"\x1b"[..], b"[1", b"4", b"t"] {
+            reporter.scan(chunk);
+        }
+        assert_eq!(replies.try_recv().unwrap(), b"\x1b[4;480;720t");
+    }
+
+    #[test]
+    fn ignores_other_sequences() {
+        let (mut reporter, replies) = reporter();
+        reporter.scan(b"\x1b[18t\x1b[8;24;80t\x1b[14m14t");
+        assert!(replies.try_recv().is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use ratatui::layout::{Position, Rect, Size};
+use ratatui::text::Span;
+
+/// Clicks on the same cell closer together than this count as a double or triple click.
+const MULTI_CLICK_TIME: Duration = Duration::from_millis(400);
+
+/// Characters which are part of a word besides letters and digits, so that a double click
+/// selects a whole path or identifier.
+const WORD_CHARACTERS: &str = "_-./~";
+
+fn is_word(symbol: &str) -> bool {
+    !symbol.is_empty()
+        && symbol
+            .chars()
+            .all(|c| c.is_alphanumeric() || WORD_CHARACTERS.contains(c))
+}
+
+/// What was last drawn in each cell, which is what selected text is copied from. The backends
+/// keep this as the TUI draws, for a command or a recording it is copied from the terminal when
+/// a selection starts.
+#[derive(Default)]
+pub(crate) struct ScreenText {
+    size: Size,
+    symbols: Vec<String>,
+}
+
+impl ScreenText {
+    /// Changes the size of the grid, which empties it.
+    pub(crate) fn resize(&mut self, size: Size) {
+        if size != self.size {
+            self.size = size;
+            self.clear();
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        let cells = self.size.width as usize * self.size.height as usize;
+        self.symbols = vec![String::new(); cells];
+    }
+
+    pub(crate) fn set(&mut self, x: u16, y: u16, symbol: &str) {
+        if x < self.size.width && y < self.size.height {
+            let index = y as usize * self.size.width as usize + x as usize;
+            symbol.clone_into(&mut self.symbols[index]);
+        }
+    }
+
+    fn get(&self, x: u16, y: u16) -> &str {
+        &self.symbols[y as usize * self.size.width as usize + x as usize]
+    }
+
+    /// Returns the columns of the word in a cell, or just the cell if it isn't part of one.
+    fn word(&self, position: Position) -> Range<u16> {
+        let Position { x, y } = position;
+        if !is_word(self.get(x, y)) {
+            return x..x + 1;
+        }
+        let start = (0..x)
+            .rev()
+            .find(|&x| !is_word(self.get(x, y)))
+            .map_or(0, |x| x + 1);
+        let end = (x..self.size.width)
+            .find(|&x| !is_word(self.get(x, y)))
+            .unwrap_or(self.size.width);
+        start..end
+    }
+
+    /// Returns the text from `start` to `end`, both included, one line for every row with the
+    /// blanks at the end of it left out.
+    fn text(&self, start: Position, end: Position) -> String {
+        let mut lines = Vec::new();
+        for y in start.y..=end.y {
+            let first = if y == start.y { start.x } else { 0 };
+            let last = if y == end.y {
+                end.x
+            } else {
+                self.size.width - 1
+            };
+            let mut line = String::new();
+            let mut x = first;
+            while x <= last {
+                let symbol = self.get(x, y);
+                line.push_str(if symbol.is_empty() { " " } else { symbol });
+                // the cells a wide character covers still hold whatever was drawn there before.
+                x += (Span::raw(symbol).width() as u16).max(1);
+            }
+            lines.push(line.trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+}
+
+/// How much a selection grows by, which depends on how many times it was clicked.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Cell,
+    Word,
+    Line,
+}
+
+/// Text selected with the mouse, from the cell where the button went down to the cell it is
+/// now over. Either may come first.
+struct Selection {
+    unit: Unit,
+    anchor: Position,
+    end: Position,
+}
+
+impl Selection {
+    /// Returns the first and last selected cells, in reading order, none if a single click
+    /// hasn't been dragged anywhere yet. The TUI may not have drawn the grid at its current size
+    /// yet, so the cells are kept to what it has drawn.
+    fn bounds(&self, text: &ScreenText) -> Option<(Position, Position)> {
+        let Size { width, height } = text.size;
+        let clicked = self.unit == Unit::Cell && self.anchor == self.end;
+        if clicked || width == 0 || height == 0 {
+            return None;
+        }
+        let clamp = |position: Position| {
+            Position::new(position.x.min(width - 1), position.y.min(height - 1))
+        };
+        let (mut start, mut end) = (clamp(self.anchor), clamp(self.end));
+        if (end.y, end.x) < (start.y, start.x) {
+            (start, end) = (end, start);
+        }
+        match self.unit {
+            Unit::Cell => {}
+            Unit::Word => {
+                start.x = text.word(start).start;
+                end.x = text.word(end).end - 1;
+            }
+            Unit::Line => {
+                start.x = 0;
+                end.x = width - 1;
+            }
+        }
+        Some((start, end))
+    }
+}
+
+/// Follows the mouse while text is being selected. A single click selects cells, a double
+/// click words and a triple click lines, and dragging extends the selection by the same unit.
+#[derive(Default)]
+pub(super) struct Selector {
+    selection: Option<Selection>,
+    dragging: bool,
+    last_click: Option<(Instant, Position, Unit)>,
+}
+
+impl Selector {
+    pub(super) fn press(&mut self, position: Position) {
+        let now = Instant::now();
+        let unit = match self.last_click {
+            Some((time, clicked, unit)) if clicked == position && now - time < MULTI_CLICK_TIME => {
+                match unit {
+                    Unit::Cell => Unit::Word,
+                    Unit::Word => Unit::Line,
+                    Unit::Line => Unit::Cell,
+                }
+            }
+            _ => Unit::Cell,
+        };
+        self.last_click = Some((now, position, unit));
+        self.selection = Some(Selection {
+            unit,
+            anchor: position,
+            end: position,
+        });
+        self.dragging = true;
+    }
+
+    pub(super) fn drag(&mut self, position: Position) {
+        if let Some(selection) = &mut self.selection
+            && self.dragging
+        {
+            selection.end = position;
+        }
+    }
+
+    pub(super) fn release(&mut self) {
+        self.dragging = false;
+    }
+
+    pub(super) fn dragging(&self) -> bool {
+        self.dragging
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.selection = None;
+        self.dragging = false;
+    }
+
+    /// Returns the selected text, none if nothing is selected.
+    pub(super) fn text(&self, text: &ScreenText) -> Option<String> {
+        let (start, end) = self.selection.as_ref()?.bounds(text)?;
+        Some(text.text(start, end))
+    }
+
+    /// Returns the selected cells as one rectangle for every row.
+    pub(super) fn areas(&self, text: &ScreenText) -> Vec<Rect> {
+        let Some((start, end)) = self.selection.as_ref().and_then(|s| s.bounds(text)) else {
+            return Vec::new();
+        };
+        (start.y..=end.y)
+            .map(|y| {
+                let first = if y == start.y { start.x } else { 0 };
+                let last = if y == end.y {
+                    end.x
+                } else {
+                    text.size.width - 1
+                };
+                Rect::new(first, y, last - first + 1, 1)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lays out lines the way a backend draws them, a wide character followed by the cell it
+    /// covers.
+    fn screen(width: u16, lines: &[&str]) -> ScreenText {
+        let mut text = ScreenText::default();
+        text.resize(Size::new(width, lines.len() as u16));
+        for (y, line) in lines.iter().enumerate() {
+            let mut x = 0;
+            for c in line.chars() {
+                let symbol = c.to_string();
+                text.set(x, y as u16, &symbol);
+                x += Span::raw(symbol).width() as u16;
+            }
+        }
+        text
+    }
+
+    fn click(selector: &mut Selector, x: u16, y: u16) {
+        selector.press(Position::new(x, y));
+        selector.release();
+    }
+
+    #[test]
+    fn a_click_selects_nothing_until_dragged() {
+        let text = screen(10, &["hello"]);
+        let mut selector = Selector::default();
+        selector.press(Position::new(1, 0));
+        assert_eq!(selector.text(&text), None);
+        selector.drag(Position::new(3, 0));
+        assert_eq!(selector.text(&text).as_deref(), Some("ell"));
+    }
+
+    #[test]
+    fn a_double_click_selects_the_word() {
+        let text = screen(24, &["cat ~/src/main_1.rs -n"]);
+        let mut selector = Selector::default();
+        click(&mut selector, 8, 0);
+        click(&mut selector, 8, 0);
+        assert_eq!(selector.text(&text).as_deref(), Some("~/src/main_1.rs"));
+        assert_eq!(selector.areas(&text), vec![Rect::new(4, 0, 15, 1)]);
+    }
+
+    #[test]
+    fn a_double_click_between_words_selects_the_cell() {
+        let text = screen(10, &["a  b"]);
+        let mut selector = Selector::default();
+        click(&mut selector, 1, 0);
+        click(&mut selector, 1, 0);
+        assert_eq!(selector.areas(&text), vec![Rect::new(1, 0, 1, 1)]);
+    }
+
+    #[test]
+    fn a_triple_click_selects_the_line() {
+        let text = screen(10, &["one", "two words", "three"]);
+        let mut selector = Selector::default();
+        for _ in 0..3 {
+            click(&mut selector, 5, 1);
+        }
+        assert_eq!(selector.text(&text).as_deref(), Some("two words"));
+        assert_eq!(selector.areas(&text), vec![Rect::new(0, 1, 10, 1)]);
+    }
+
+    #[test]
+    fn wide_characters_are_copied_once() {
+        let text = screen(10, &["a日本b"]);
+        let mut selector = Selector::default();
+        selector.press(Position::new(0, 0));
+        selector.drag(Position::new(5, 0));
+        assert_eq!(selector.text(&text).as_deref(), Some("a日本b"));
+    }
+
+    #[test]
+    fn rows_are_joined_without_trailing_blanks() {
+        let text = screen(8, &["first", "second", "third"]);
+        let mut selector = Selector::default();
+        // dragging backwards selects the same cells.
+        selector.press(Position::new(2, 2));
+        selector.drag(Position::new(3, 0));
+        assert_eq!(selector.text(&text).as_deref(), Some("st\nsecond\nthi"));
+        assert_eq!(
+            selector.areas(&text),
+            vec![
+                Rect::new(3, 0, 5, 1),
+                Rect::new(0, 1, 8, 1),
+                Rect::new(0, 2, 3, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn selections_are_kept_to_the_grid() {
+        let text = screen(4, &["abcd", "efgh"]);
+        let mut selector = Selector::default();
+        selector.press(Position::new(2, 0));
+        selector.drag(Position::new(9, 9));
+        assert_eq!(selector.text(&text).as_deref(), Some("cd\nefgh"));
+    }
+}
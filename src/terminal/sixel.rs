@@ -0,0 +1,687 @@
+use std::sync::{Arc, Mutex};
+
+use ratatui::layout::Rect;
+use ratatui::text::Span;
+
+use crate::terminal::ScreenSize;
+use crate::terminal::overlay::{OverlayLayer, TRANSPARENT};
+
+// images are cut off here, no window is anywhere near as big.
+const MAX_IMAGE_SIZE: usize = 4096;
+
+/// The colors sixel images start out with, those of the VT340.
+const DEFAULT_PALETTE: [u32; 16] = [
+    0x000000, 0x3333cc, 0xcc2121, 0x33cc33, 0xcc33cc, 0x33cccc, 0xcccc33, 0x878787, 0x424242,
+    0x545499, 0x994242, 0x549954, 0x995499, 0x549999, 0x999954, 0xcccccc,
+];
+
+/// Adds a digit or separator to numeric parameters, which are 0 when left out.
+fn push_param(params: &mut Vec<u32>, byte: u8) {
+    match (byte, params.last_mut()) {
+        (b';', _) => {
+            if params.is_empty() {
+                params.push(0);
+            }
+            params.push(0);
+        }
+        (_, Some(param)) => {
+            *param = param
+                .saturating_mul(10)
+                .saturating_add((byte - b'0') as u32)
+        }
+        (_, None) => params.push((byte - b'0') as u32),
+    }
+}
+
+fn percent_to_level(percent: u32) -> u32 {
+    percent.min(100) * 255 / 100
+}
+
+/// Converts a sixel HLS color, whose hue starts at blue rather than red, to RGB.
+fn hls_to_rgb(hue: u32, lightness: u32, saturation: u32) -> u32 {
+    let hue = ((hue + 240) % 360) as f32;
+    let lightness = lightness.min(100) as f32 / 100.0;
+    let saturation = saturation.min(100) as f32 / 100.0;
+
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let level = |c: f32| ((c + m) * 255.0).round() as u32;
+    level(r) << 16 | level(g) << 8 | level(b)
+}
+
+/// Decodes the data of a sixel image, the part between `q` and the string terminator.
+struct SixelDecoder {
+    palette: Vec<u32>,
+    color: u32,
+    /// The color of pixels which are never drawn, transparent ones let the cells underneath
+    /// show through.
+    background: u32,
+    x: usize,
+    /// The top row of the current band of six.
+    y: usize,
+    repeat: usize,
+    rows: Vec<Vec<u32>>,
+    /// The command whose parameters are being collected.
+    command: Option<u8>,
+    params: Vec<u32>,
+}
+
+impl SixelDecoder {
+    fn new(transparent: bool) -> Self {
+        let mut palette = vec![0; 256];
+        palette[..16].copy_from_slice(&DEFAULT_PALETTE);
+        Self {
+            background: if transparent { TRANSPARENT } else { palette[0] },
+            color: palette[0],
+            palette,
+            x: 0,
+            y: 0,
+            repeat: 1,
+            rows: Vec::new(),
+            command: None,
+            params: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, byte: u8) {
+        if self.command.is_some() {
+            if matches!(byte, b'0'..=b'9' | b';') {
+                push_param(&mut self.params, byte);
+                return;
+            }
+            self.finish_command();
+        }
+        match byte {
+            b'#' | b'!' | b'"' => self.command = Some(byte),
+            b'$' => self.x = 0,
+            b'-' => {
+                self.x = 0;
+                self.y += 6;
+            }
+            b'?'..=b'~' => {
+                let bits = byte - b'?';
+                for _ in 0..std::mem::replace(&mut self.repeat, 1) {
+                    for bit in (0..6).filter(|bit| bits & 1 << bit != 0) {
+                        self.set(self.x, self.y + bit, self.color);
+                    }
+                    self.x += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish_command(&mut self) {
+        let params = std::mem::take(&mut self.params);
+        match self.command.take() {
+            Some(b'#') => {
+                let Some(&register) = params.first() else {
+                    return;
+                };
+                let register = register as usize % self.palette.len();
+                match params[1..] {
+                    [1, h, l, s] => self.palette[register] = hls_to_rgb(h, l, s),
+                    [2, r, g, b] => {
+                        let level = percent_to_level;
+                        self.palette[register] = level(r) << 16 | level(g) << 8 | level(b);
+                    }
+                    _ => {}
+                }
+                self.color = self.palette[register];
+            }
+            Some(b'!') => {
+                self.repeat = params.first().map_or(1, |&count| count.max(1) as usize);
+                self.repeat = self.repeat.min(MAX_IMAGE_SIZE);
+            }
+            // the raster attributes only give the aspect ratio and a size to expect, the image
+            // is as big as what is actually drawn.
+            _ => {}
+        }
+    }
+
+    fn set(&mut self, x: usize, y: usize, color: u32) {
+        if x >= MAX_IMAGE_SIZE || y >= MAX_IMAGE_SIZE {
+            return;
+        }
+        if self.rows.len() <= y {
+            self.rows.resize_with(y + 1, Vec::new);
+        }
+        let row = &mut self.rows[y];
+        if row.len() <= x {
+            row.resize(x + 1, TRANSPARENT);
+        }
+        row[x] = color;
+    }
+
+    /// Returns the width, height and pixels of the image, none if nothing was drawn.
+    fn finish(mut self) -> Option<(usize, usize, Vec<u32>)> {
+        if self.command.is_some() {
+            self.finish_command();
+        }
+        let width = self
+            .rows
+            .iter()
+            .map(Vec::len)
+            .max()
+            .filter(|width| *width > 0)?;
+        let height = self.rows.len();
+        let mut pixels = Vec::with_capacity(width * height);
+        for mut row in self.rows {
+            row.resize(width, TRANSPARENT);
+            pixels.extend(row);
+        }
+        if self.background != TRANSPARENT {
+            for pixel in pixels.iter_mut().filter(|pixel| **pixel == TRANSPARENT) {
+                *pixel = self.background;
+            }
+        }
+        Some((width, height, pixels))
+    }
+}
+
+enum State {
+    Ground,
+    /// A UTF-8 character, with the number of bytes still to come.
+    Utf8(Vec<u8>, usize),
+    Escape,
+    /// Inside `ESC [`, collecting the parameters.
+    Csi(Vec<u16>, Option<u16>, bool),
+    /// Inside `ESC P`, holding the bytes until it's clear whether this is sixel.
+    DcsHeader(Vec<u8>, Vec<u32>),
+    /// Inside a control string which isn't sixel, passed on until the string terminator.
+    Passthrough,
+    /// Inside an OSC, APC, PM or SOS string, passed on until BEL or the string terminator.
+    /// None of it is text, however printable it is.
+    CommandString,
+    Sixel(Box<SixelDecoder>),
+    SixelEscape(Box<SixelDecoder>),
+}
+
+/// Takes sixel images out of the output before it reaches os-terminal, which doesn't support
+/// them, and lays them over the display at the cell the cursor is on. An image stays until a
+/// cell it covers is written to or erased.
+///
+/// The cursor is followed through the output's cursor moves, line breaks and text. Scrolling
+/// moves the cells underneath an image, which the image can't follow, so the images on the
+/// lines which scroll are dropped.
+pub(crate) struct SixelFilter {
+    size: Arc<Mutex<ScreenSize>>,
+    layer: Arc<Mutex<OverlayLayer>>,
+    state: State,
+    column: u16,
+    row: u16,
+    /// The column and row saved with `ESC 7` or `CSI s`.
+    saved: (u16, u16),
+    /// The first and last row which scroll, set with `CSI r`. None is the whole screen.
+    margins: Option<(u16, u16)>,
+}
+
+impl SixelFilter {
+    pub(crate) fn new(size: Arc<Mutex<ScreenSize>>, layer: Arc<Mutex<OverlayLayer>>) -> Self {
+        Self {
+            size,
+            layer,
+            state: State::Ground,
+            column: 0,
+            row: 0,
+            saved: (0, 0),
+            margins: None,
+        }
+    }
+
+    /// Appends the output to `filtered`, without any sixel images.
+    pub(crate) fn filter(&mut self, output: &[u8], filtered: &mut Vec<u8>) {
+        let size = *self.size.lock().unwrap();
+        let layer = self.layer.clone();
+        let mut layer = layer.lock().unwrap();
+        for &byte in output {
+            self.state = match (std::mem::replace(&mut self.state, State::Ground), byte) {
+                (State::Sixel(decoder), 0x1b) => State::SixelEscape(decoder),
+                (State::Sixel(mut decoder), _) => {
+                    decoder.feed(byte);
+                    State::Sixel(decoder)
+                }
+                (State::SixelEscape(decoder), b'\\') => {
+                    if let Some((width, height, pixels)) = decoder.finish() {
+                        let (column, row) = (self.column as usize, self.row as usize);
+                        layer.add_image(column, row, size, width, height, pixels);
+                    }
+                    State::Ground
+                }
+                // an image which isn't terminated properly is dropped.
+                (State::SixelEscape(_), _) => self.escape(byte, filtered, size, &mut layer),
+                (State::Passthrough, 0x1b) => State::Escape,
+                (State::Passthrough, _) => {
+                    filtered.push(byte);
+                    State::Passthrough
+                }
+                (State::CommandString, 0x1b) => State::Escape,
+                (State::CommandString, _) => {
+                    filtered.push(byte);
+                    if byte == 0x07 {
+                        State::Ground
+                    } else {
+                        State::CommandString
+                    }
+                }
+                (State::DcsHeader(mut held, mut params), _) => {
+                    held.push(byte);
+                    match byte {
+                        b'0'..=b'9' | b';' => {
+                            push_param(&mut params, byte);
+                            State::DcsHeader(held, params)
+                        }
+                        // the second parameter says whether unset pixels are transparent.
+                        b'q' => {
+                            State::Sixel(Box::new(SixelDecoder::new(params.get(1) == Some(&1))))
+                        }
+                        _ => {
+                            filtered.extend_from_slice(&held);
+                            State::Passthrough
+                        }
+                    }
+                }
+                (State::Escape, 0x1b) => {
+                    filtered.push(byte);
+                    State::Escape
+                }
+                (_, 0x1b) => State::Escape,
+                (State::Escape, _) => self.escape(byte, filtered, size, &mut layer),
+                (State::Csi(mut params, current, private), _) => {
+                    filtered.push(byte);
+                    match byte {
+                        b'0'..=b'9' => {
+                            let digit = (byte - b'0') as u16;
+                            let current = current
+                                .unwrap_or(0)
+                                .saturating_mul(10)
+                                .saturating_add(digit);
+                            State::Csi(params, Some(current), private)
+                        }
+                        b';' => {
+                            params.push(current.unwrap_or(0));
+                            State::Csi(params, None, private)
+                        }
+                        0x40..=0x7e => {
+                            params.extend(current);
+                            if !private {
+                                self.csi(byte, &params, size, &mut layer);
+                            }
+                            State::Ground
+                        }
+                        _ => State::Csi(params, current, true),
+                    }
+                }
+                (State::Utf8(mut bytes, remaining), 0x80..=0xbf) => {
+                    filtered.push(byte);
+                    bytes.push(byte);
+                    if remaining > 1 {
+                        State::Utf8(bytes, remaining - 1)
+                    } else {
+                        let width = std::str::from_utf8(&bytes)
+                            .map_or(1, |text| Span::raw(text).width() as u16);
+                        self.print(width, size, &mut layer);
+                        State::Ground
+                    }
+                }
+                (_, _) => {
+                    filtered.push(byte);
+                    self.ground(byte, size, &mut layer)
+                }
+            };
+        }
+    }
+
+    /// Handles text and control characters.
+    fn ground(&mut self, byte: u8, size: ScreenSize, layer: &mut OverlayLayer) -> State {
+        match byte {
+            b'\r' => self.column = 0,
+            b'\n' | 0x0b | 0x0c => self.line_feed(size, layer),
+            0x08 => self.column = self.column.min(last(size.cols)).saturating_sub(1),
+            b'\t' => self.column = (self.column / 8 + 1).saturating_mul(8).min(last(size.cols)),
+            0x20..=0x7e => self.print(1, size, layer),
+            0xc0..=0xdf => return State::Utf8(vec![byte], 1),
+            0xe0..=0xef => return State::Utf8(vec![byte], 2),
+            0xf0..=0xf7 => return State::Utf8(vec![byte], 3),
+            _ => {}
+        }
+        State::Ground
+    }
+
+    /// Handles the byte after an escape.
+    fn escape(
+        &mut self,
+        byte: u8,
+        filtered: &mut Vec<u8>,
+        size: ScreenSize,
+        layer: &mut OverlayLayer,
+    ) -> State {
+        match byte {
+            b'P' => State::DcsHeader(vec![0x1b, byte], Vec::new()),
+            b'[' => {
+                filtered.extend_from_slice(&[0x1b, byte]);
+                State::Csi(Vec::new(), None, false)
+            }
+            b']' | b'_' | b'^' | b'X' => {
+                filtered.extend_from_slice(&[0x1b, byte]);
+                State::CommandString
+            }
+            _ => {
+                filtered.extend_from_slice(&[0x1b, byte]);
+                match byte {
+                    b'D' => self.line_feed(size, layer),
+                    b'E' => {
+                        self.column = 0;
+                        self.line_feed(size, layer);
+                    }
+                    b'M' => self.reverse_line_feed(size, layer),
+                    b'7' => self.saved = (self.column, self.row),
+                    b'8' => (self.column, self.row) = self.saved,
+                    // a full reset clears the screen.
+                    b'c' => {
+                        (self.column, self.row) = (0, 0);
+                        self.saved = (0, 0);
+                        self.margins = None;
+                        layer.erase_images(Rect::new(0, 0, u16::MAX, u16::MAX));
+                    }
+                    _ => {}
+                }
+                State::Ground
+            }
+        }
+    }
+
+    fn csi(&mut self, action: u8, params: &[u16], size: ScreenSize, layer: &mut OverlayLayer) {
+        let param = |index: usize| params.get(index).copied().unwrap_or(0);
+        // moves are by at least one, and positions count from one.
+        let count = param(0).max(1);
+        let (column, row) = (self.column, self.row);
+        let (last_column, last_row) = (last(size.cols), last(size.rows));
+        match action {
+            b'A' => self.row = row.saturating_sub(count),
+            b'B' => self.row = row.saturating_add(count).min(last_row),
+            b'C' => self.column = column.saturating_add(count).min(last_column),
+            b'D' => self.column = column.min(last_column).saturating_sub(count),
+            b'E' => (self.column, self.row) = (0, row.saturating_add(count).min(last_row)),
+            b'F' => (self.column, self.row) = (0, row.saturating_sub(count)),
+            b'G' | b'`' => self.column = (count - 1).min(last_column),
+            b'd' => self.row = (count - 1).min(last_row),
+            b'H' | b'f' => {
+                self.row = (count - 1).min(last_row);
+                self.column = (param(1).max(1) - 1).min(last_column);
+            }
+            b's' => self.saved = (column, row),
+            b'u' => (self.column, self.row) = self.saved,
+            b'r' => {
+                let bottom = if param(1) == 0 { size.rows } else { param(1) };
+                self.margins = Some((count - 1, bottom.max(1) - 1));
+                (self.column, self.row) = (0, 0);
+            }
+            // scrolling the region, and inserting or deleting lines, which scrolls the lines
+            // below the cursor.
+            b'S' | b'T' => self.scrolled(self.margins(size), layer),
+            b'L' | b'M' => {
+                let (top, bottom) = self.margins(size);
+                if (top..=bottom).contains(&row) {
+                    self.scrolled((row, bottom), layer);
+                }
+            }
+            b'J' => {
+                let erased = match param(0) {
+                    0 => Rect::new(0, row, size.cols, size.rows.saturating_sub(row)),
+                    1 => Rect::new(0, 0, size.cols, row + 1),
+                    _ => Rect::new(0, 0, u16::MAX, u16::MAX),
+                };
+                layer.erase_images(erased);
+            }
+            b'K' => {
+                let erased = match param(0) {
+                    0 => Rect::new(column, row, size.cols.saturating_sub(column), 1),
+                    1 => Rect::new(0, row, column + 1, 1),
+                    _ => Rect::new(0, row, size.cols, 1),
+                };
+                layer.erase_images(erased);
+            }
+            _ => {}
+        }
+    }
+
+    /// Writes a character at the cursor. After the last column the cursor waits there until the
+    /// next character wraps it to the next line.
+    fn print(&mut self, width: u16, size: ScreenSize, layer: &mut OverlayLayer) {
+        if self.column.saturating_add(width) > size.cols {
+            self.column = 0;
+            self.line_feed(size, layer);
+        }
+        layer.erase_images(Rect::new(self.column, self.row, width.max(1), 1));
+        self.column = self.column.saturating_add(width);
+    }
+
+    /// Returns the first and last row which scroll.
+    fn margins(&self, size: ScreenSize) -> (u16, u16) {
+        let last_row = last(size.rows);
+        let (top, bottom) = self.margins.unwrap_or((0, last_row));
+        (top.min(last_row), bottom.min(last_row))
+    }
+
+    /// Drops the images on the rows which scrolled.
+    fn scrolled(&self, (top, bottom): (u16, u16), layer: &mut OverlayLayer) {
+        if top <= bottom {
+            layer.erase_images(Rect::new(0, top, u16::MAX, bottom - top + 1));
+        }
+    }
+
+    /// Moves the cursor down a line, which scrolls the region at its bottom margin.
+    fn line_feed(&mut self, size: ScreenSize, layer: &mut OverlayLayer) {
+        let (top, bottom) = self.margins(size);
+        if self.row == bottom {
+            self.scrolled((top, bottom), layer);
+        } else {
+            self.row = self.row.saturating_add(1).min(last(size.rows));
+        }
+    }
+
+    /// Moves the cursor up a line, which scrolls the region back at its top margin.
+    fn reverse_line_feed(&mut self, size: ScreenSize, layer: &mut OverlayLayer) {
+        let (top, bottom) = self.margins(size);
+        if self.row == top {
+            self.scrolled((top, bottom), layer);
+        } else {
+            self.row = self.row.saturating_sub(1);
+        }
+    }
+}
+
+/// Returns the last column or row of a screen this many wide or high.
+fn last(count: u16) -> u16 {
+    count.saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a cell is one pixel wide and as high as a band of sixels, so a band covers a row.
+    const SIZE: ScreenSize = ScreenSize {
+        cols: 10,
+        rows: 4,
+        cell_width: 1,
+        cell_height: 6,
+    };
+    /// A red image two cells wide.
+    const IMAGE: &[u8] = b"\x1bPq#1;2;100;0;0~~\x1b\\";
+
+    fn filter() -> SixelFilter {
+        SixelFilter::new(Arc::new(Mutex::new(SIZE)), Arc::default())
+    }
+
+    fn decode(data: &[u8], transparent: bool) -> (usize, usize, Vec<u32>) {
+        let mut decoder = SixelDecoder::new(transparent);
+        for &byte in data {
+            decoder.feed(byte);
+        }
+        decoder.finish().unwrap()
+    }
+
+    /// Returns the cells images cover after the output, a line of `#` and `.` for every row.
+    fn covered(output: &[u8]) -> Vec<String> {
+        let mut filter = filter();
+        filter.filter(output, &mut Vec::new());
+        let (width, height) = (SIZE.cols as usize, SIZE.rows as usize * 6);
+        let mut frame = vec![0; width * height];
+        filter
+            .layer
+            .lock()
+            .unwrap()
+            .composite(&mut frame, width, &(0..height));
+        (0..SIZE.rows as usize)
+            .map(|row| {
+                let row = &frame[row * 6 * width..][..width];
+                row.iter()
+                    .map(|&pixel| if pixel == 0 { '.' } else { '#' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn colors_come_from_the_palette() {
+        assert_eq!(decode(b"#3@", false), (1, 1, vec![0x33cc33]));
+        assert_eq!(decode(b"#1;2;100;50;0@", false), (1, 1, vec![0xff7f00]));
+        // the hue of HLS colors starts at blue.
+        assert_eq!(decode(b"#1;1;0;50;100@", false), (1, 1, vec![0x0000ff]));
+        assert_eq!(decode(b"#1;1;120;50;100@", false), (1, 1, vec![0xff0000]));
+        // a register keeps its color when it's selected again.
+        assert_eq!(
+            decode(b"#1;2;0;0;100#3@#1@", false),
+            (2, 1, vec![0x33cc33, 0x0000ff])
+        );
+    }
+
+    #[test]
+    fn sixels_are_repeated() {
+        assert_eq!(decode(b"#3!3@@", false), (4, 1, vec![0x33cc33; 4]));
+        assert_eq!(decode(b"#3!@", false), (1, 1, vec![0x33cc33]));
+    }
+
+    #[test]
+    fn bands_go_down_six_rows() {
+        let (width, height, pixels) = decode(b"#3~-@", false);
+        assert_eq!((width, height), (1, 7));
+        assert_eq!(pixels, vec![0x33cc33; 7]);
+    }
+
+    #[test]
+    fn carriage_returns_draw_over_the_band() {
+        let (width, height, pixels) = decode(b"#3@@$#1A", false);
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(pixels, vec![0x33cc33, 0x33cc33, 0x3333cc, 0x000000]);
+    }
+
+    #[test]
+    fn undrawn_pixels_are_the_background_unless_transparent() {
+        assert_eq!(decode(b"#3A", false).2, vec![0x000000, 0x33cc33]);
+        assert_eq!(decode(b"#3A", true).2, vec![TRANSPARENT, 0x33cc33]);
+    }
+
+    #[test]
+    fn images_are_placed_at_the_cursor() {
+        let output = [b"ab\x1b[2;3H", IMAGE].concat();
+        assert_eq!(
+            covered(&output),
+            ["..........", "..##......", "..........", ".........."]
+        );
+        let output = [b"\x1b[3;8H\x1b[2A\x1b[3D", IMAGE].concat();
+        assert_eq!(
+            covered(&output),
+            ["....##....", "..........", "..........", ".........."]
+        );
+        let output = [b"\x1b[2;1H\x1b[7G\x1b[4d\x1b[B\x1b[C", IMAGE].concat();
+        assert_eq!(
+            covered(&output),
+            ["..........", "..........", "..........", ".......##."]
+        );
+        let output = [b"\x1b7\x1b[3;3H\r\x1b8\x1b[E", IMAGE].concat();
+        assert_eq!(
+            covered(&output),
+            ["..........", "##........", "..........", ".........."]
+        );
+    }
+
+    #[test]
+    fn images_are_erased_with_their_cells() {
+        let image = [b"\x1b[2;3H", IMAGE].concat();
+        let erasing: [&[u8]; 4] = [b"\x1b[2;4Hx", b"\x1b[K", b"\x1b[1;1H\x1b[J", b"\x1bc"];
+        for erase in erasing {
+            let output = [&image[..], erase].concat();
+            assert_eq!(covered(&output), [".........."; 4], "{:?}", erase);
+        }
+        let kept: [&[u8]; 3] = [b"\x1b[2;6Hx", b"\x1b[2;2H\x1b[1K", b"\x1b[3;1H\x1b[J"];
+        for keep in kept {
+            let output = [&image[..], keep].concat();
+            assert_eq!(covered(&output)[1], "..##......", "{:?}", keep);
+        }
+    }
+
+    #[test]
+    fn images_are_dropped_when_their_lines_scroll() {
+        let image = [b"\x1b[2;3H", IMAGE].concat();
+        let scrolling: [&[u8]; 5] = [
+            b"\x1b[4;1H\n",
+            b"\x1b[4;10Hxy",
+            b"\x1b[S",
+            b"\x1b[1;1H\x1bM",
+            b"\x1b[L",
+        ];
+        for scroll in scrolling {
+            let output = [&image[..], scroll].concat();
+            assert_eq!(covered(&output), [".........."; 4], "{:?}", scroll);
+        }
+        // lines outside of the region, or above the cursor, stay where they are.
+        let kept: [&[u8]; 3] = [b"\x1b[3;4r\x1b[4;1H\n", b"\x1b[3;1H\n", b"\x1b[3;1H\x1b[M"];
+        for keep in kept {
+            let output = [&image[..], keep].concat();
+            assert_eq!(covered(&output)[1], "..##......", "{:?}", keep);
+        }
+    }
+
+    #[test]
+    fn command_strings_are_not_text() {
+        let strings: [&[u8]; 6] = [
+            b"\x1b]0;a long window title\x07",
+            b"\x1b]0;a long window title\x1b\\",
+            b"\x1b]8;;https://example.com\x1b\\",
+            b"\x1b_application program command\x1b\\",
+            b"\x1b^privacy message\x1b\\",
+            b"\x1bXstart of string\x1b\\",
+        ];
+        for string in strings {
+            let mut filter = filter();
+            let mut filtered = Vec::new();
+            let output = [b"ab", string, b"c"].concat();
+            filter.filter(&output, &mut filtered);
+            assert_eq!(filtered, output);
+            assert_eq!((filter.column, filter.row), (3, 0), "{:?}", string);
+        }
+    }
+
+    #[test]
+    fn command_strings_split_across_output() {
+        let mut filter = filter();
+        let mut filtered = Vec::new();
+        for chunk in [&b"\x1b]"[..], b"2;title", b"\x1b", b"\\x"] {
+            filter.filter(chunk, &mut filtered);
+        }
+        assert_eq!(filtered, b"\x1b]2;title\x1b\\x");
+        assert_eq!((filter.column, filter.row), (1, 0));
+    }
+}
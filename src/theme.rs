@@ -0,0 +1,187 @@
+use os_terminal::Palette;
+
+use crate::options::Options;
+
+/// A color as red, green and blue.
+pub type Rgb = (u8, u8, u8);
+
+/// The colors text is drawn with in the window.
+#[derive(Clone)]
+pub struct Theme {
+    /// The name the theme is selected by.
+    pub name: String,
+    /// The default text color.
+    pub foreground: Rgb,
+    /// The default background color.
+    pub background: Rgb,
+    /// The color of the cursor, which is drawn in reverse video if there is none. os-terminal
+    /// always draws the cursor in reverse video, so this only applies to
+    /// [`RenderMode::Pixel`](crate::RenderMode::Pixel).
+    pub cursor: Option<Rgb>,
+    /// The 16 ANSI colors, the normal colors followed by their bright versions.
+    pub ansi_colors: [Rgb; 16],
+}
+
+/// A built in theme, as its name, foreground, background, cursor and ANSI colors.
+type BuiltinTheme = (&'static str, u32, u32, Option<u32>, [u32; 16]);
+
+const BUILTIN_THEMES: &[BuiltinTheme] = &[
+    // os-terminal's own default.
+    (
+        "default",
+        0xf5f5f5,
+        0x151515,
+        None,
+        [
+            0x151515, 0xac4142, 0x90a959, 0xf4bf75, 0x6a9fb5, 0xaa759f, 0x75b5aa, 0xd0d0d0,
+            0x505050, 0xac4142, 0x90a959, 0xf4bf75, 0x6a9fb5, 0xaa759f, 0x75b5aa, 0xf5f5f5,
+        ],
+    ),
+    (
+        "solarized-dark",
+        0x839496,
+        0x002b36,
+        Some(0x93a1a1),
+        [
+            0x073642, 0xdc322f, 0x859900, 0xb58900, 0x268bd2, 0xd33682, 0x2aa198, 0xeee8d5,
+            0x002b36, 0xcb4b16, 0x586e75, 0x657b83, 0x839496, 0x6c71c4, 0x93a1a1, 0xfdf6e3,
+        ],
+    ),
+    (
+        "solarized-light",
+        0x657b83,
+        0xfdf6e3,
+        Some(0x586e75),
+        [
+            0x073642, 0xdc322f, 0x859900, 0xb58900, 0x268bd2, 0xd33682, 0x2aa198, 0xeee8d5,
+            0x002b36, 0xcb4b16, 0x586e75, 0x657b83, 0x839496, 0x6c71c4, 0x93a1a1, 0xfdf6e3,
+        ],
+    ),
+    (
+        "gruvbox-dark",
+        0xebdbb2,
+        0x282828,
+        Some(0xebdbb2),
+        [
+            0x282828, 0xcc241d, 0x98971a, 0xd79921, 0x458588, 0xb16286, 0x689d6a, 0xa89984,
+            0x928374, 0xfb4934, 0xb8bb26, 0xfabd2f, 0x83a598, 0xd3869b, 0x8ec07c, 0xebdbb2,
+        ],
+    ),
+    (
+        "gruvbox-light",
+        0x3c3836,
+        0xfbf1c7,
+        Some(0x3c3836),
+        [
+            0xfbf1c7, 0xcc241d, 0x98971a, 0xd79921, 0x458588, 0xb16286, 0x689d6a, 0x7c6f64,
+            0x928374, 0x9d0006, 0x79740e, 0xb57614, 0x076678, 0x8f3f71, 0x427b58, 0x3c3836,
+        ],
+    ),
+    (
+        "high-contrast",
+        0xffffff,
+        0x000000,
+        Some(0xffff00),
+        [
+            0x000000, 0xff5555, 0x55ff55, 0xffff55, 0x5599ff, 0xff55ff, 0x55ffff, 0xe0e0e0,
+            0x808080, 0xff8080, 0x80ff80, 0xffff80, 0x80bbff, 0xff80ff, 0x80ffff, 0xffffff,
+        ],
+    ),
+];
+
+fn hex_to_rgb(hex: u32) -> Rgb {
+    ((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
+}
+
+/// Parses a color written as `#rrggbb`.
+fn parse_color(text: &str) -> Option<Rgb> {
+    let hex = text.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    u32::from_str_radix(hex, 16).ok().map(hex_to_rgb)
+}
+
+impl Theme {
+    /// The themes which are always available: `default`, `solarized-dark`, `solarized-light`,
+    /// `gruvbox-dark`, `gruvbox-light` and `high-contrast`.
+    pub fn builtin() -> Vec<Theme> {
+        BUILTIN_THEMES
+            .iter()
+            .map(|(name, foreground, background, cursor, ansi_colors)| Theme {
+                name: name.to_string(),
+                foreground: hex_to_rgb(*foreground),
+                background: hex_to_rgb(*background),
+                cursor: cursor.map(hex_to_rgb),
+                ansi_colors: ansi_colors.map(hex_to_rgb),
+            })
+            .collect()
+    }
+
+    /// Changes one of the colors by name, as found in a config file. The names are
+    /// `foreground`, `background`, `cursor` and `color0` to `color15`, the colors are written
+    /// as `#rrggbb`.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let color =
+            parse_color(value).ok_or_else(|| format!("{:?} is not a #rrggbb color", value))?;
+        match name {
+            "foreground" => self.foreground = color,
+            "background" => self.background = color,
+            "cursor" => self.cursor = Some(color),
+            _ => {
+                let index = name
+                    .strip_prefix("color")
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .filter(|index| *index < 16)
+                    .ok_or_else(|| format!("unknown color {:?}", name))?;
+                self.ansi_colors[index] = color;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn palette(&self) -> Palette {
+        Palette {
+            foreground: self.foreground,
+            background: self.background,
+            ansi_colors: self.ansi_colors,
+        }
+    }
+}
+
+/// The themes the window can switch between, in the order they are switched through.
+pub(crate) struct Themes {
+    themes: Vec<Theme>,
+    current: usize,
+}
+
+impl Themes {
+    pub(crate) fn new(options: &Options) -> Result<Self, String> {
+        let current = options
+            .themes
+            .iter()
+            .position(|theme| theme.name == options.theme)
+            .ok_or_else(|| {
+                let names = options.themes.iter().map(|theme| theme.name.as_str());
+                format!(
+                    "unknown theme {:?}, the themes are {}",
+                    options.theme,
+                    names.collect::<Vec<_>>().join(", ")
+                )
+            })?;
+        Ok(Self {
+            themes: options.themes.clone(),
+            current,
+        })
+    }
+
+    pub(crate) fn current(&self) -> &Theme {
+        &self.themes[self.current]
+    }
+
+    /// Switches to the next theme, going back to the first after the last one.
+    pub(crate) fn next(&mut self) -> &Theme {
+        self.current = (self.current + 1) % self.themes.len();
+        self.current()
+    }
+}
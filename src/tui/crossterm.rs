@@ -8,13 +8,17 @@ use ratatui::{
     Terminal,
     backend::CrosstermBackend,
     crossterm::{
-        event::{self, DisableMouseCapture, EnableMouseCapture, Event},
+        event::{
+            self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste,
+            EnableMouseCapture, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        },
         execute,
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     },
 };
 
-use crate::tui::{app::App, screen::Screen};
+use crate::options::Options;
+use crate::tui::{app::{self, TuiApp}, screen::{Screen, ScreenEvent}};
 
 pub(crate) struct CrosstermScreen;
 
@@ -22,9 +26,9 @@ impl<W: Write> Screen<W> for CrosstermScreen {
 
     type Backend = CrosstermBackend<W>;
 
-    fn poll_and_read(&self, timeout: Duration) -> Result<Option<Event>,Box<dyn Error>> {
+    fn poll_and_read(&self, timeout: Duration) -> Result<Option<ScreenEvent>,Box<dyn Error>> {
         if event::poll(timeout)? {
-            Ok(Some(event::read()?))
+            Ok(Some(event::read()?.into()))
         } else {
             Ok(None)
         }
@@ -51,34 +55,39 @@ impl<W: Write> Screen<W> for CrosstermScreen {
 }
 
 
-pub fn run<W: Write, S: Screen<W>>(
-    tick_rate: Duration,
-    enhanced_graphics: bool,
+pub fn run<A: TuiApp, W: Write, S: Screen<W>>(
+    mut app: A,
+    options: &Options,
     mut stdout: W,
     screen: S,
 ) -> Result<(), Box<dyn Error>> {
     // setup terminal
     screen.enable_raw_mode()?;
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // pastes come as a single event, rather than as keys.
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableBracketedPaste)?;
     let backend = screen.create_backend(stdout);
     let mut terminal = Terminal::new(backend)?;
+    // the flags go through the backend, which is what the window follows them in.
+    let flags = options.keyboard_enhancement;
+    if !flags.is_empty() {
+        execute!(terminal.backend_mut(), PushKeyboardEnhancementFlags(flags))?;
+    }
 
-    // create app and run it
-    let app = App::new("Crossterm Demo", enhanced_graphics);
-    let app_result = app.run(&mut terminal, tick_rate, &screen);
+    // run the app
+    let app_result = app::run(&mut app, &mut terminal, options, &screen);
 
     // restore terminal
     screen.disable_raw_mode()?;
+    if !flags.is_empty() {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableBracketedPaste
     )?;
     terminal.show_cursor()?;
 
-    if let Err(err) = app_result {
-        println!("{err:?}");
-    }
-
-    Ok(())
+    app_result
 }
@@ -1,14 +1,15 @@
 use std::error::Error;
-use std::time::Duration;
+use crate::options::Options;
 use crate::tui::crossterm::CrosstermScreen;
 
 pub(crate) mod screen;
-mod app;
+pub(crate) mod app;
 pub(crate) mod crossterm;
-mod ui;
 
-pub(crate) fn run_no_win() -> Result<(), Box<dyn Error>> {
-    let tick_rate = Duration::from_millis(250);
-    crossterm::run(tick_rate, true, std::io::stdout(), CrosstermScreen)?;
+pub use app::TuiApp;
+
+/// Runs the application in the terminal the process was started from.
+pub fn run_in_terminal<A: TuiApp>(app: A, options: Options) -> Result<(), Box<dyn Error>> {
+    crossterm::run(app, &options, std::io::stdout(), CrosstermScreen)?;
     Ok(())
 }
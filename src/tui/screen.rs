@@ -4,12 +4,25 @@ use std::time::Duration;
 use ratatui::crossterm::event::Event;
 use std::error::Error;
 
+/// Something a screen passes on to the application.
+pub enum ScreenEvent {
+    /// Input from the terminal or the window.
+    Input(Event),
+    /// The user asked to close the window.
+    CloseRequested,
+}
+
+impl From<Event> for ScreenEvent {
+    fn from(event: Event) -> Self {
+        Self::Input(event)
+    }
+}
 
 pub trait Screen<W: Write> {
 
     type Backend: Backend + std::io::Write;
 
-    fn poll_and_read(&self, timeout: Duration) -> Result<Option<Event>,Box<dyn Error>>;
+    fn poll_and_read(&self, timeout: Duration) -> Result<Option<ScreenEvent>,Box<dyn Error>>;
 
     fn enable_raw_mode(&self) -> Result<(),Box<dyn Error>>;
 
@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+
+use os_terminal_ratatui::{Options, TuiApp, run_headless};
+use ratatui::Frame;
+use ratatui::crossterm::event::{Event, KeyCode, KeyEventKind};
+use ratatui::style::{Color, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+
+/// Draws a bordered greeting in a few colors, and quits on q.
+struct Greeting {
+    quit: bool,
+}
+
+impl TuiApp for Greeting {
+    fn draw(&mut self, frame: &mut Frame) {
+        let text = vec![
+            Line::from("Hello".bold()),
+            Line::from(vec!["red".fg(Color::Red), " on ".into(), "blue".bg(Color::Blue)]),
+        ];
+        let block = Block::bordered().title("headless");
+        frame.render_widget(Paragraph::new(text).block(block), frame.area());
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        if let Event::Key(key) = event
+            && key.kind == KeyEventKind::Press
+            && key.code == KeyCode::Char('q')
+        {
+            self.quit = true;
+        }
+    }
+
+    fn tick(&mut self) {}
+
+    fn should_quit(&self) -> bool {
+        self.quit
+    }
+}
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+/// Runs the script with `{snapshot}` replaced by where the snapshot goes, and compares the
+/// snapshot with the fixture. Setting `UPDATE_SNAPSHOTS` writes the fixture instead.
+fn check_snapshot(script: &str, name: &str) {
+    let dir = std::env::temp_dir().join(format!("headless-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+    let snapshot = dir.join(name);
+    let script_path = dir.join("script.txt");
+    let script = script.replace("{snapshot}", snapshot.to_str().unwrap());
+    std::fs::write(&script_path, script).unwrap();
+
+    run_headless(Greeting { quit: false }, Options::default(), &script_path).unwrap();
+
+    let actual = std::fs::read(&snapshot).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let expected = fixture(name);
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&expected, &actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read(&expected).unwrap();
+    assert!(actual == expected, "{} differs from the fixture", name);
+}
+
+#[test]
+fn greeting_matches_fixture() {
+    check_snapshot("size 240 80\nsnapshot {snapshot}\nkey q\n", "greeting.png");
+}